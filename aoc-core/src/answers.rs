@@ -0,0 +1,185 @@
+//! Answers already verified for each day's own input, kept in
+//! `answers.txt` next to `input.txt`, and checking current solutions
+//! against them.
+//!
+//! Each line of the file is `<part>: <answer>`. Answers spanning several
+//! lines are written with `\n` between them. Lines starting with `#` are
+//! comments.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::Day;
+
+pub const FILE: &str = "answers.txt";
+
+/// Verified answers by part.
+pub type Known = BTreeMap<u32, String>;
+
+pub fn parse(text: &str) -> Result<Known, String> {
+    let mut known = Known::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let (part, answer) = line.split_once(':').ok_or_else(|| format!("expected <part>: <answer>, found >{}<", line))?;
+        let part = part.trim().parse().map_err(|_| format!("could not parse >{}<", part))?;
+        if known.insert(part, answer.trim().replace("\\n", "\n")).is_some() {
+            return Err(format!("part {} is listed twice", part));
+        }
+    }
+    Ok(known)
+}
+
+impl Day<'_> {
+    pub fn answers_path(&self) -> PathBuf {
+        PathBuf::from(self.dir).join(self.input).with_file_name(FILE)
+    }
+
+    /// The day's verified answers; none if it has no answers file yet.
+    pub fn known_answers(&self) -> Result<Known, String> {
+        let path = self.answers_path();
+        match fs::read_to_string(&path) {
+            Ok(text) => parse(&text).map_err(|err| format!("{}: {}", path.display(), err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Known::new()),
+            Err(err) => Err(format!("could not read {} ({})", path.display(), err)),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Verdict {
+    Pass,
+    Fail { expected: String },
+    /// No answer has been verified for this part.
+    Unknown,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Check {
+    pub day: u32,
+    pub part: u32,
+    /// What the solver returned, or why it failed.
+    pub answer: Result<String, String>,
+    pub verdict: Verdict,
+}
+
+/// Solves the given parts of a day from its own input and compares each
+/// answer with the verified one. A solver that errors, or input that
+/// cannot be read or parsed, fails the check if an answer is known.
+pub fn check(day: &Day, parts: &[u32]) -> Result<Vec<Check>, String> {
+    let known = day.known_answers()?;
+    let input = day.load_input()
+        .map_err(|err| err.to_string())
+        .and_then(|input| day.parse(&input).map_err(|err| err.to_string()));
+    let mut checks = Vec::new();
+    for &part in parts {
+        let solve = day.part(part).ok_or_else(|| format!("{} has no part {}", day.title(), part))?;
+        let answer = match &input {
+            Ok(input) => solve(input).map_err(|err| err.to_string()),
+            Err(err) => Err(err.clone()),
+        };
+        let verdict = match (known.get(&part), &answer) {
+            (None, _) => Verdict::Unknown,
+            (Some(expected), Ok(answer)) if answer == expected => Verdict::Pass,
+            (Some(expected), _) => Verdict::Fail { expected: expected.clone() },
+        };
+        checks.push(Check { day: day.number, part, answer, verdict });
+    }
+    Ok(checks)
+}
+
+// Keeps multi-line answers on one row of the table.
+fn one_line(answer: &str) -> String {
+    answer.replace('\n', "\\n")
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Verdict::Pass => "PASS",
+            Verdict::Fail { .. } => "FAIL",
+            Verdict::Unknown => "UNKNOWN",
+        })
+    }
+}
+
+pub fn table(checks: &[Check]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{:<4} {:<4} {:<7} answer", "day", "part", "result");
+    for check in checks {
+        let answer = match &check.answer {
+            Ok(answer) => one_line(answer),
+            Err(err) => format!("error: {}", err),
+        };
+        let _ = write!(out, "{:<4} {:<4} {:<7} {}", format!("{:02}", check.day), check.part, check.verdict, answer);
+        if let Verdict::Fail { expected } = &check.verdict {
+            let _ = write!(out, " (expected {})", one_line(expected));
+        }
+        let _ = writeln!(out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod answers_tests {
+    use super::*;
+    use crate::scratch::Scratch;
+    use crate::{Solution, Solver};
+    use std::error::Error;
+
+    struct Words;
+
+    impl Solution for Words {
+        type Input = Vec<String>;
+
+        fn parse(input: &str) -> Result<Vec<String>, Box<dyn Error>> {
+            Ok(input.trim().split(',').map(String::from).collect())
+        }
+
+        fn part_1(words: &Vec<String>) -> Result<impl fmt::Display, Box<dyn Error>> {
+            Ok(words.len())
+        }
+
+        fn part_2(words: &Vec<String>) -> Result<impl fmt::Display, Box<dyn Error>> {
+            Ok(words.join("\n"))
+        }
+    }
+
+    fn day(dir: &str) -> Day<'_> {
+        Day { number: 9, dir, input: "input.txt", expects: "words", solver: Solver::of::<Words>() }
+    }
+
+    #[test]
+    fn parses_answers_files() {
+        let known = parse("# verified\n1: 42\n\n2: 0\\n7\n").unwrap();
+        assert_eq!(Known::from([(1, "42".to_string()), (2, "0\n7".to_string())]), known);
+        assert_eq!(Err("could not parse >one<".to_string()), parse("one: 1"));
+        assert_eq!(Err("part 1 is listed twice".to_string()), parse("1: 1\n1: 2"));
+        assert!(parse("1 = 1").is_err());
+    }
+
+    #[test]
+    fn checks_answers_against_the_known_ones() {
+        let scratch = Scratch::with_files("aoc-core-answers-check", &[("input.txt", "a,b\n"), (FILE, "1: 2\n")]);
+        let dir = scratch.path().to_str().unwrap();
+        let checks = check(&day(dir), &[1, 2]).unwrap();
+        assert_eq!(Verdict::Pass, checks[0].verdict);
+        assert_eq!((Ok("a\nb".to_string()), Verdict::Unknown), (checks[1].answer.clone(), checks[1].verdict.clone()));
+        fs::write(day(dir).answers_path(), "1: 4\n2: a\\nb\n").unwrap();
+        let checks = check(&day(dir), &[1, 2]).unwrap();
+        assert_eq!(Verdict::Fail { expected: "4".to_string() }, checks[0].verdict);
+        assert_eq!(Verdict::Pass, checks[1].verdict);
+        assert_eq!("day  part result  answer\n09   1    FAIL    2 (expected 4)\n09   2    PASS    a\\nb\n", table(&checks));
+    }
+
+    #[test]
+    fn unreadable_input_fails_known_parts() {
+        let scratch = Scratch::with_files("aoc-core-answers-missing-input", &[(FILE, "1: 2\n")]);
+        let dir = scratch.path().to_str().unwrap();
+        let checks = check(&day(dir), &[1, 2]).unwrap();
+        assert!(checks[0].answer.is_err());
+        assert_eq!(Verdict::Fail { expected: "2".to_string() }, checks[0].verdict);
+        assert_eq!(Verdict::Unknown, checks[1].verdict);
+    }
+}
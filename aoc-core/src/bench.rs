@@ -0,0 +1,309 @@
+//! Timing of solutions: warmup runs, then a number of timed samples
+//! summarised as min, median, mean and standard deviation. Parsing is
+//! timed as a stage of its own, and each part from input parsed up
+//! front. Results can be written as text, JSON or CSV, and compared
+//! against a baseline saved from an earlier run to flag regressions.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Write as _};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::Day;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Stats {
+    pub samples: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub mean: Duration,
+    /// Sample standard deviation; zero for fewer than two samples.
+    pub stddev: Duration,
+}
+
+impl Stats {
+    /// Panics if there are no samples.
+    pub fn from_samples(samples: &[Duration]) -> Self {
+        assert!(!samples.is_empty(), "no samples to summarise");
+        let mut sorted = samples.to_vec();
+        sorted.sort();
+        let n = sorted.len();
+        let median = match n % 2 {
+            0 => (sorted[n / 2 - 1] + sorted[n / 2]) / 2,
+            _ => sorted[n / 2],
+        };
+        let mean = sorted.iter().sum::<Duration>() / n as u32;
+        let variance = match n {
+            1 => 0.0,
+            _ => sorted.iter()
+                .map(|s| (s.as_secs_f64() - mean.as_secs_f64()).powi(2))
+                .sum::<f64>() / (n - 1) as f64,
+        };
+        Stats { samples: n, min: sorted[0], median, mean, stddev: Duration::from_secs_f64(variance.sqrt()) }
+    }
+}
+
+/// What is being timed: parsing the input, or solving a part from it.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Stage {
+    Parse,
+    Part(u32),
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stage::Parse => write!(f, "parse"),
+            Stage::Part(part) => write!(f, "part {}", part),
+        }
+    }
+}
+
+impl FromStr for Stage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.strip_prefix("part ").map(str::parse) {
+            _ if s == "parse" => Ok(Stage::Parse),
+            Some(Ok(part)) => Ok(Stage::Part(part)),
+            _ => Err(format!("could not parse >{}<", s)),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Measurement {
+    pub day: u32,
+    pub stage: Stage,
+    pub stats: Stats,
+}
+
+fn sample<T>(warmup: u32, samples: u32, mut f: impl FnMut() -> Result<T, Box<dyn Error>>) -> Result<Stats, Box<dyn Error>> {
+    for _ in 0..warmup {
+        std::hint::black_box(f()?);
+    }
+    let mut times = Vec::new();
+    for _ in 0..samples.max(1) {
+        let start = Instant::now();
+        std::hint::black_box(f()?);
+        times.push(start.elapsed());
+    }
+    Ok(Stats::from_samples(&times))
+}
+
+/// Runs one stage `warmup` times untimed, then `samples` times timed.
+pub fn measure(day: &Day, stage: Stage, input: &str, warmup: u32, samples: u32) -> Result<Measurement, Box<dyn Error>> {
+    let stats = match stage {
+        Stage::Parse => sample(warmup, samples, || day.parse(std::hint::black_box(input)))?,
+        Stage::Part(part) => {
+            let solve = day.part(part).ok_or_else(|| format!("{} has no part {}", day.title(), part))?;
+            let parsed = day.parse(input)?;
+            sample(warmup, samples, || solve(std::hint::black_box(&parsed)))?
+        },
+    };
+    Ok(Measurement { day: day.number, stage, stats })
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!("unknown format >{}<; expected text, json or csv", s)),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "day,stage,samples,min_ns,median_ns,mean_ns,stddev_ns";
+
+fn nanos(stats: &Stats) -> [u128; 4] {
+    [stats.min, stats.median, stats.mean, stats.stddev].map(|d| d.as_nanos())
+}
+
+pub fn format(measurements: &[Measurement], format: Format) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Text => {
+            let _ = writeln!(out, "{:<14} {:>12} {:>12} {:>12} {:>12}", "", "min", "median", "mean", "stddev");
+            for m in measurements {
+                let s = &m.stats;
+                let name = format!("Day {:02} {}", m.day, m.stage);
+                let _ = writeln!(out, "{:<14} {:>12.2?} {:>12.2?} {:>12.2?} {:>12.2?}",
+                                 name, s.min, s.median, s.mean, s.stddev);
+            }
+        },
+        Format::Json => {
+            let _ = writeln!(out, "[");
+            for (i, m) in measurements.iter().enumerate() {
+                let [min, median, mean, stddev] = nanos(&m.stats);
+                let comma = if i + 1 < measurements.len() { "," } else { "" };
+                let _ = writeln!(
+                    out,
+                    "  {{\"day\": {}, \"stage\": \"{}\", \"samples\": {}, \"min_ns\": {}, \"median_ns\": {}, \"mean_ns\": {}, \"stddev_ns\": {}}}{}",
+                    m.day, m.stage, m.stats.samples, min, median, mean, stddev, comma
+                );
+            }
+            let _ = writeln!(out, "]");
+        },
+        Format::Csv => {
+            let _ = writeln!(out, "{}", CSV_HEADER);
+            for m in measurements {
+                let [min, median, mean, stddev] = nanos(&m.stats);
+                let _ = writeln!(out, "{},{},{},{},{},{},{}", m.day, m.stage, m.stats.samples, min, median, mean, stddev);
+            }
+        },
+    }
+    out
+}
+
+/// Median times keyed by day and stage.
+pub type Baseline = BTreeMap<(u32, Stage), Duration>;
+
+// Pulls the value of `"key": value` out of one line of the JSON this
+// module writes, without any quotes.
+fn json_field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let rest = line[line.find(&format!("\"{}\":", key))? + key.len() + 3..].trim_start();
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next(),
+        None => rest.split([',', '}']).next().map(str::trim),
+    }
+}
+
+/// Reads a baseline back from the JSON or CSV `format` wrote.
+pub fn parse_baseline(text: &str) -> Result<Baseline, String> {
+    let mut baseline = Baseline::new();
+    if text.trim_start().starts_with('[') {
+        for line in text.lines().filter(|line| line.contains('{')) {
+            let field = |key| json_field(line, key).ok_or_else(|| format!("missing >{}< in >{}<", key, line.trim()));
+            let (day, stage, median) = (field("day")?, field("stage")?, field("median_ns")?);
+            baseline.insert((number(day)?, stage.parse()?), Duration::from_nanos(number(median)?));
+        }
+        return Ok(baseline);
+    }
+    let mut lines = text.lines();
+    if lines.next() != Some(CSV_HEADER) {
+        return Err(format!("expected a JSON array or a CSV file starting >{}<", CSV_HEADER));
+    }
+    for line in lines.filter(|line| !line.is_empty()) {
+        match line.split(',').collect::<Vec<&str>>()[..] {
+            [day, stage, _, _, median, _, _] => {
+                baseline.insert((number(day)?, stage.parse()?), Duration::from_nanos(number(median)?));
+            },
+            _ => return Err(format!("expected 7 fields in >{}<", line)),
+        }
+    }
+    Ok(baseline)
+}
+
+fn number<T: FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("could not parse >{}<", s))
+}
+
+/// How one stage's median moved relative to the baseline.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Comparison {
+    pub day: u32,
+    pub stage: Stage,
+    pub baseline: Duration,
+    pub current: Duration,
+}
+
+impl Comparison {
+    /// Relative change in percent; positive is slower.
+    pub fn change(&self) -> f64 {
+        100.0 * (self.current.as_secs_f64() / self.baseline.as_secs_f64().max(f64::MIN_POSITIVE) - 1.0)
+    }
+
+    pub fn regressed(&self, threshold: f64) -> bool {
+        self.change() > threshold
+    }
+}
+
+/// Pairs each measurement with its baseline; stages missing from the
+/// baseline are skipped.
+pub fn compare(measurements: &[Measurement], baseline: &Baseline) -> Vec<Comparison> {
+    measurements.iter()
+        .filter_map(|m| baseline.get(&(m.day, m.stage)).map(|&base| Comparison {
+            day: m.day,
+            stage: m.stage,
+            baseline: base,
+            current: m.stats.median,
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod bench_tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    fn measurement(day: u32, stage: Stage, samples: &[u64]) -> Measurement {
+        let samples = samples.iter().map(|&n| ms(n)).collect::<Vec<Duration>>();
+        Measurement { day, stage, stats: Stats::from_samples(&samples) }
+    }
+
+    #[test]
+    fn summarises_samples() {
+        let stats = measurement(1, Stage::Part(1), &[4, 2, 8, 6]).stats;
+        assert_eq!((4, ms(2), ms(5), ms(5)), (stats.samples, stats.min, stats.median, stats.mean));
+        assert_eq!(2581, stats.stddev.as_micros());
+        let single = measurement(1, Stage::Part(1), &[3]).stats;
+        assert_eq!((ms(3), Duration::ZERO), (single.median, single.stddev));
+    }
+
+    #[test]
+    fn names_stages() {
+        assert_eq!(Ok(Stage::Parse), "parse".parse());
+        assert_eq!(Ok(Stage::Part(2)), Stage::Part(2).to_string().parse());
+        assert!("part two".parse::<Stage>().is_err());
+    }
+
+    #[test]
+    fn writes_csv_and_json() {
+        let ms = [measurement(3, Stage::Part(2), &[1, 3])];
+        assert_eq!(format!("{}\n3,part 2,2,1000000,2000000,2000000,1414214\n", CSV_HEADER), format(&ms, Format::Csv));
+        assert_eq!(
+            "[\n  {\"day\": 3, \"stage\": \"part 2\", \"samples\": 2, \"min_ns\": 1000000, \"median_ns\": 2000000, \
+             \"mean_ns\": 2000000, \"stddev_ns\": 1414214}\n]\n",
+            format(&ms, Format::Json)
+        );
+    }
+
+    #[test]
+    fn reads_back_either_format_as_a_baseline() {
+        let ms = [measurement(3, Stage::Part(2), &[1, 3]), measurement(5, Stage::Parse, &[7])];
+        let expected = Baseline::from([((3, Stage::Part(2)), Duration::from_millis(2)), ((5, Stage::Parse), Duration::from_millis(7))]);
+        assert_eq!(Ok(expected.clone()), parse_baseline(&format(&ms, Format::Csv)));
+        assert_eq!(Ok(expected), parse_baseline(&format(&ms, Format::Json)));
+        assert!(parse_baseline(&format(&ms, Format::Text)).is_err());
+    }
+
+    #[test]
+    fn flags_medians_slower_than_the_threshold() {
+        let baseline = Baseline::from([((1, Stage::Part(1)), ms(100)), ((1, Stage::Part(2)), ms(100))]);
+        let current = [
+            measurement(1, Stage::Part(1), &[105]),
+            measurement(1, Stage::Part(2), &[120]),
+            measurement(1, Stage::Parse, &[1]),
+        ];
+        let comparisons = compare(&current, &baseline);
+        assert_eq!(2, comparisons.len());
+        assert!(!comparisons[0].regressed(10.0));
+        assert!(comparisons[1].regressed(10.0));
+        assert!((comparisons[1].change() - 20.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,318 @@
+//! What every day's solution shares: the `Solution` trait, a descriptor
+//! the `aoc` runner registers, loading of the puzzle input, and running
+//! and printing the parts. Timing lives in `bench`, checking against
+//! verified answers in `answers`, and temp dirs for tests in `scratch`.
+
+pub mod answers;
+pub mod bench;
+pub mod scratch;
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A part's answer, ready to print.
+pub type Answer = Result<String, Box<dyn Error>>;
+
+/// A day's puzzle: the input is parsed once, then both parts are solved
+/// from the parsed form.
+pub trait Solution {
+    type Input;
+
+    fn parse(input: &str) -> Result<Self::Input, Box<dyn Error>>;
+    fn part_1(input: &Self::Input) -> Result<impl fmt::Display, Box<dyn Error>>;
+    fn part_2(input: &Self::Input) -> Result<impl fmt::Display, Box<dyn Error>>;
+}
+
+/// Parsed input whose type only the day's own `Solution` knows.
+pub type Parsed = Box<dyn Any>;
+
+/// A `Solution` with its types erased, so every day fits one registry.
+#[derive(Debug, Clone, Copy)]
+pub struct Solver {
+    pub parse: fn(&str) -> Result<Parsed, Box<dyn Error>>,
+    pub part_1: fn(&Parsed) -> Answer,
+    pub part_2: fn(&Parsed) -> Answer,
+}
+
+impl Solver {
+    pub const fn of<S: Solution>() -> Self where S::Input: 'static {
+        Solver { parse: parse_erased::<S>, part_1: part_1_erased::<S>, part_2: part_2_erased::<S> }
+    }
+}
+
+fn parse_erased<S: Solution>(input: &str) -> Result<Parsed, Box<dyn Error>> where S::Input: 'static {
+    Ok(Box::new(S::parse(input)?))
+}
+
+// Panics if `input` came from another day's parser.
+fn downcast<S: Solution>(input: &Parsed) -> &S::Input where S::Input: 'static {
+    input.downcast_ref().expect("input was parsed by a different solution")
+}
+
+fn part_1_erased<S: Solution>(input: &Parsed) -> Answer where S::Input: 'static {
+    Ok(S::part_1(downcast::<S>(input))?.to_string())
+}
+
+fn part_2_erased<S: Solution>(input: &Parsed) -> Answer where S::Input: 'static {
+    Ok(S::part_2(downcast::<S>(input))?.to_string())
+}
+
+/// Everything the runner needs to know about one day.
+#[derive(Debug, Clone, Copy)]
+pub struct Day<'a> {
+    pub number: u32,
+    /// The day's crate directory; set it to `env!("CARGO_MANIFEST_DIR")`.
+    pub dir: &'a str,
+    /// The input file read when no other source is given, relative to `dir`.
+    pub input: &'static str,
+    /// What the input looks like, for error messages.
+    pub expects: &'static str,
+    pub solver: Solver,
+}
+
+/// Where to read a day's puzzle input from.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Source {
+    /// The day's own input file.
+    Default,
+    File(PathBuf),
+    Stdin,
+    Inline(String),
+}
+
+impl Source {
+    /// `--input <text>` gives the input inline, `-` reads it from stdin
+    /// and any other argument that is neither a flag nor the value of one
+    /// of `value_flags` is a path. At most one source may be given.
+    pub fn from_args(args: &[String], value_flags: &[&str]) -> Result<Self, String> {
+        let mut found = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--input" {
+                let text = args.next().ok_or("--input needs a value")?;
+                found.push(Source::Inline(text.clone()));
+            } else if value_flags.contains(&arg.as_str()) {
+                args.next();
+            } else if arg == "-" {
+                found.push(Source::Stdin);
+            } else if !arg.starts_with("--") {
+                found.push(Source::File(PathBuf::from(arg)));
+            }
+        }
+        match found.len() {
+            0 => Ok(Source::Default),
+            1 => Ok(found.remove(0)),
+            _ => Err(format!("expected one input source, found {}", found.len())),
+        }
+    }
+}
+
+/// Input that could not be read, or was empty, explained in terms of the
+/// day that wanted it.
+#[derive(Debug)]
+pub struct InputError {
+    pub day: u32,
+    pub expects: &'static str,
+    pub source: String,
+    pub problem: String,
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Day {:02} expects {}, but {} {}; pass a path, - for stdin, or --input <text>",
+            self.day, self.expects, self.source, self.problem
+        )
+    }
+}
+
+impl Error for InputError {}
+
+impl Day<'_> {
+    pub fn title(&self) -> String {
+        format!("Day {:02}", self.number)
+    }
+
+    pub fn load_input(&self) -> Result<String, InputError> {
+        self.read_input(&Source::Default)
+    }
+
+    pub fn read_input(&self, source: &Source) -> Result<String, InputError> {
+        let (name, read) = match source {
+            Source::Default => {
+                let path = Path::new(self.dir).join(self.input);
+                (path.display().to_string(), fs::read_to_string(&path))
+            },
+            Source::File(path) => (path.display().to_string(), fs::read_to_string(path)),
+            Source::Stdin => ("stdin".to_string(), io::read_to_string(io::stdin())),
+            Source::Inline(text) => ("--input".to_string(), Ok(text.clone())),
+        };
+        let error = |problem: String| InputError {
+            day: self.number,
+            expects: self.expects,
+            source: name.clone(),
+            problem,
+        };
+        match read {
+            Ok(input) if input.trim().is_empty() => Err(error("is empty".to_string())),
+            Ok(input) => Ok(input),
+            Err(err) => Err(error(format!("could not be read ({})", err))),
+        }
+    }
+
+    pub fn parse(&self, input: &str) -> Result<Parsed, Box<dyn Error>> {
+        (self.solver.parse)(input)
+            .map_err(|err| format!("{} expects {}, but {}", self.title(), self.expects, err).into())
+    }
+
+    /// The solver for part 1 or 2, given the parsed input.
+    pub fn part(&self, part: u32) -> Option<fn(&Parsed) -> Answer> {
+        match part {
+            1 => Some(self.solver.part_1),
+            2 => Some(self.solver.part_2),
+            _ => None,
+        }
+    }
+}
+
+/// Solves the given parts of a day, printing each answer.
+pub fn run(day: &Day, source: &Source, parts: &[u32]) -> Result<(), Box<dyn Error>> {
+    let input = day.parse(&day.read_input(source)?)?;
+    for &part in parts {
+        let solve = day.part(part).ok_or_else(|| format!("{} has no part {}", day.title(), part))?;
+        println!("{}; Part {} = {}", day.title(), part, solve(&input)?);
+    }
+    Ok(())
+}
+
+/// The parts asked for with `--part <p>`, or both.
+pub fn parts_from_args(args: &[String]) -> Result<Vec<u32>, String> {
+    match args.iter().position(|arg| arg == "--part").map(|i| args.get(i + 1)) {
+        Some(Some(part)) => part.parse().map(|part| vec![part]).map_err(|_| format!("could not parse >{}<", part)),
+        Some(None) => Err("--part needs a value".to_string()),
+        None => Ok(vec![1, 2]),
+    }
+}
+
+/// Prints an error the way a user should see it and exits with status 1.
+pub fn report(result: Result<(), Box<dyn Error>>) {
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// The whole of a day's `main`: solves the parts asked for from the input
+/// named on the command line.
+pub fn main(day: &Day) {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    report(run_with_args(day, &args));
+}
+
+fn run_with_args(day: &Day, args: &[String]) -> Result<(), Box<dyn Error>> {
+    run(day, &Source::from_args(args, &["--part"])?, &parts_from_args(args)?)
+}
+
+#[cfg(test)]
+mod aoc_core_tests {
+    use super::*;
+
+    struct Letters;
+
+    impl Solution for Letters {
+        type Input = String;
+
+        fn parse(input: &str) -> Result<String, Box<dyn Error>> {
+            match input.chars().find(|c| !c.is_alphabetic()) {
+                Some(c) => Err(format!("found >{}<", c).into()),
+                None => Ok(input.to_string()),
+            }
+        }
+
+        fn part_1(input: &String) -> Result<impl fmt::Display, Box<dyn Error>> {
+            Ok(input.len())
+        }
+
+        fn part_2(_: &String) -> Result<impl fmt::Display, Box<dyn Error>> {
+            Err::<usize, _>("no answer".into())
+        }
+    }
+
+    const DAY: Day = Day {
+        number: 7,
+        dir: ".",
+        input: "no-such-input.txt",
+        expects: "some letters",
+        solver: Solver::of::<Letters>(),
+    };
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn inline(text: &str) -> Source {
+        Source::Inline(text.to_string())
+    }
+
+    #[test]
+    fn looks_parts_up_by_number() {
+        let input = DAY.parse("abc").unwrap();
+        assert_eq!("Day 07", DAY.title());
+        assert_eq!("3", DAY.part(1).unwrap()(&input).unwrap());
+        assert!(DAY.part(2).unwrap()(&input).is_err());
+        assert!(DAY.part(3).is_none());
+    }
+
+    #[test]
+    fn explains_unparsable_input() {
+        assert_eq!("Day 07 expects some letters, but found >1<", DAY.parse("ab1").unwrap_err().to_string());
+        assert!(run(&DAY, &inline("ab1"), &[1]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "parsed by a different solution")]
+    fn refuses_input_parsed_by_another_day() {
+        DAY.part(1).unwrap()(&(Box::new(7) as Parsed)).ok();
+    }
+
+    #[test]
+    fn picks_the_input_source_from_arguments() {
+        assert_eq!(Ok(Source::Default), Source::from_args(&args("--day 3"), &["--day"]));
+        assert_eq!(Ok(Source::File("in.txt".into())), Source::from_args(&args("--day 3 in.txt"), &["--day"]));
+        assert_eq!(Ok(Source::Stdin), Source::from_args(&args("- --verbose"), &[]));
+        assert_eq!(Ok(inline("1-2")), Source::from_args(&args("--input 1-2"), &[]));
+        assert!(Source::from_args(&args("a.txt -"), &[]).is_err());
+        assert!(Source::from_args(&args("--input"), &[]).is_err());
+    }
+
+    #[test]
+    fn picks_parts_from_arguments() {
+        assert_eq!(Ok(vec![1, 2]), parts_from_args(&args("in.txt")));
+        assert_eq!(Ok(vec![2]), parts_from_args(&args("--part 2")));
+        assert!(parts_from_args(&args("--part")).is_err());
+        assert!(parts_from_args(&args("--part two")).is_err());
+    }
+
+    #[test]
+    fn explains_missing_and_empty_input() {
+        assert_eq!("abc", DAY.read_input(&inline("abc")).unwrap());
+        let err = DAY.load_input().unwrap_err();
+        assert!(err.to_string().starts_with("Day 07 expects some letters, but ./no-such-input.txt could not be read"), "{}", err);
+        assert_eq!(
+            "Day 07 expects some letters, but --input is empty; pass a path, - for stdin, or --input <text>",
+            DAY.read_input(&inline(" \n")).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn run_reports_unknown_parts_and_failing_solvers() {
+        assert!(run(&DAY, &inline("abc"), &[1]).is_ok());
+        assert_eq!("Day 07 has no part 3", run(&DAY, &inline("abc"), &[3]).unwrap_err().to_string());
+        assert_eq!("no answer", run(&DAY, &inline("abc"), &[2]).unwrap_err().to_string());
+    }
+}
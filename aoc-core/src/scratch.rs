@@ -0,0 +1,44 @@
+//! Scratch directories for tests that need real files.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A directory under the system temp dir, removed again on drop, so a
+/// failing test does not leave its files behind.
+pub struct Scratch {
+    dir: PathBuf,
+}
+
+impl Scratch {
+    /// Creates an empty `<name>-<pid>` directory; `name` must be unique
+    /// among the tests running at the same time. Panics if it cannot.
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("scratch dir can be created");
+        Scratch { dir }
+    }
+
+    /// Like `new`, with the given files written into it.
+    pub fn with_files(name: &str, files: &[(&str, &str)]) -> Self {
+        let scratch = Scratch::new(name);
+        for (file, text) in files {
+            fs::write(scratch.join(file), text).expect("scratch file can be written");
+        }
+        scratch
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn join(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
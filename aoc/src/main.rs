@@ -0,0 +1,314 @@
+//! Runs any of the days' solutions from one place.
+//!
+//!     aoc run --day 3 [--part 2] [<path> | - | --input <text>]
+//!     aoc run --all
+//!     aoc bench --all [--samples 20] [--format csv] [--baseline base.csv]
+//!     aoc check [--day 3]
+//!     aoc list
+//!
+//! A single day reads its own `input.txt` unless given a path, `-` for
+//! stdin, or the input itself with `--input`. `bench` times parsing apart
+//! from the parts and writes its results to stdout or `--output`; with
+//! `--baseline` it fails if any median is more than `--threshold` percent
+//! slower than the baseline's. `check` compares every answer with the
+//! day's `answers.txt` and fails if any differs.
+
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use aoc_core::answers::{self, Verdict};
+use aoc_core::bench::{self, Format, Stage};
+use aoc_core::{Day, Source};
+
+/// Every day the runner knows about. Adding a day means adding it here.
+const DAYS: &[Day] = &[
+    day_01::DAY,
+    day_02::DAY,
+    day_03::DAY,
+    day_04::DAY,
+    day_05::DAY,
+];
+
+const USAGE: &str = "\
+usage: aoc run --day <n> [--part <p>] [<path> | - | --input <text>]
+       aoc run --all [--part <p>]
+       aoc bench (--day <n> [<path> | - | --input <text>] | --all) [--part <p>]
+                 [--warmup <n>] [--samples <n>] [--format text|json|csv] [--output <path>]
+                 [--baseline <path>] [--threshold <percent>]
+       aoc check [--day <n>] [--part <p>]
+       aoc list";
+
+const BENCH_FLAGS: &[&str] = &[
+    "--day", "--part", "--warmup", "--samples", "--format", "--output", "--baseline", "--threshold",
+];
+
+#[derive(PartialEq, Debug, Clone)]
+struct BenchOptions {
+    warmup: u32,
+    samples: u32,
+    format: Format,
+    output: Option<PathBuf>,
+    baseline: Option<PathBuf>,
+    /// How many percent slower than the baseline counts as a regression.
+    threshold: f64,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self { warmup: 2, samples: 10, format: Format::Text, output: None, baseline: None, threshold: 10.0 }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+enum Command {
+    /// Runs the given parts of each day; no days means every day.
+    Run { days: Vec<u32>, parts: Vec<u32>, source: Source },
+    Bench { days: Vec<u32>, parts: Vec<u32>, source: Source, options: BenchOptions },
+    Check { days: Vec<u32>, parts: Vec<u32> },
+    List,
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn value<'a>(args: &'a [String], flag: &str) -> Result<Option<&'a str>, String> {
+    match flag_value(args, flag) {
+        None if args.iter().any(|arg| arg == flag) => Err(format!("{} needs a value", flag)),
+        value => Ok(value),
+    }
+}
+
+fn parsed<T: std::str::FromStr>(args: &[String], flag: &str) -> Result<Option<T>, String> {
+    value(args, flag)?
+        .map(|n| n.parse().map_err(|_| format!("could not parse >{}<", n)))
+        .transpose()
+}
+
+fn number(args: &[String], flag: &str) -> Result<Option<u32>, String> {
+    parsed(args, flag)
+}
+
+/// The days, parts and input source shared by `run` and `bench`.
+fn selection(args: &[String], value_flags: &[&str]) -> Result<(Vec<u32>, Vec<u32>, Source), String> {
+    let all = args.iter().any(|arg| arg == "--all");
+    let days = match (number(args, "--day")?, all) {
+        (Some(day), false) => vec![day],
+        (None, true) => Vec::new(),
+        _ => return Err(format!("{} needs either --day <n> or --all", args[0])),
+    };
+    let parts = aoc_core::parts_from_args(args)?;
+    let source = Source::from_args(&args[1..], value_flags)?;
+    if all && source != Source::Default {
+        return Err("an input can only be given for a single --day".to_string());
+    }
+    Ok((days, parts, source))
+}
+
+fn bench_options(args: &[String]) -> Result<BenchOptions, String> {
+    let mut options = BenchOptions::default();
+    options.warmup = number(args, "--warmup")?.unwrap_or(options.warmup);
+    options.samples = number(args, "--samples")?.unwrap_or(options.samples);
+    if let Some(format) = value(args, "--format")? {
+        options.format = format.parse()?;
+    }
+    options.output = value(args, "--output")?.map(PathBuf::from);
+    options.baseline = value(args, "--baseline")?.map(PathBuf::from);
+    options.threshold = parsed(args, "--threshold")?.unwrap_or(options.threshold);
+    Ok(options)
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    match args.first().map(String::as_str) {
+        Some("run") => {
+            let (days, parts, source) = selection(args, &["--day", "--part"])?;
+            Ok(Command::Run { days, parts, source })
+        },
+        Some("bench") => {
+            let (days, parts, source) = selection(args, BENCH_FLAGS)?;
+            Ok(Command::Bench { days, parts, source, options: bench_options(args)? })
+        },
+        Some("check") => {
+            let days = number(args, "--day")?.into_iter().collect();
+            Ok(Command::Check { days, parts: aoc_core::parts_from_args(args)? })
+        },
+        Some("list") => Ok(Command::List),
+        Some(other) => Err(format!("unknown command >{}<", other)),
+        None => Err("missing command".to_string()),
+    }
+}
+
+fn find(number: u32) -> Result<&'static Day<'static>, String> {
+    DAYS.iter()
+        .find(|day| day.number == number)
+        .ok_or_else(|| format!("day {} is not registered", number))
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    aoc_core::report(match parse_args(&args) {
+        Ok(command) => execute(command),
+        Err(msg) => Err(format!("{}\n{}", msg, USAGE).into()),
+    });
+}
+
+/// The registered days asked for; none means every day.
+fn selected(days: Vec<u32>) -> Result<Vec<&'static Day<'static>>, String> {
+    match days.is_empty() {
+        true => Ok(DAYS.iter().collect()),
+        false => days.into_iter().map(find).collect(),
+    }
+}
+
+fn execute(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Run { days, parts, source } => {
+            for day in selected(days)? {
+                aoc_core::run(day, &source, &parts)?;
+            }
+        },
+        Command::Bench { days, parts, source, options } => run_bench(selected(days)?, &parts, &source, &options)?,
+        Command::Check { days, parts } => {
+            let mut checks = Vec::new();
+            for day in selected(days)? {
+                checks.extend(answers::check(day, &parts)?);
+            }
+            print!("{}", answers::table(&checks));
+            let failed = checks.iter().filter(|check| matches!(check.verdict, Verdict::Fail { .. })).count();
+            if failed > 0 {
+                return Err(format!("{} of {} answers did not match", failed, checks.len()).into());
+            }
+        },
+        Command::List => {
+            for day in DAYS {
+                println!("{} ({})", day.title(), day.dir);
+            }
+        },
+    }
+    Ok(())
+}
+
+fn run_bench(days: Vec<&Day>, parts: &[u32], source: &Source, options: &BenchOptions) -> Result<(), Box<dyn Error>> {
+    let mut measurements = Vec::new();
+    for day in days {
+        let input = day.read_input(source)?;
+        let stages = std::iter::once(Stage::Parse).chain(parts.iter().map(|&part| Stage::Part(part)));
+        for stage in stages {
+            measurements.push(bench::measure(day, stage, &input, options.warmup, options.samples)?);
+        }
+    }
+    let results = bench::format(&measurements, options.format);
+    match &options.output {
+        Some(path) => fs::write(path, results).map_err(|err| format!("could not write {} ({})", path.display(), err))?,
+        None => print!("{}", results),
+    }
+    let Some(path) = &options.baseline else { return Ok(()) };
+    let text = fs::read_to_string(path).map_err(|err| format!("could not read {} ({})", path.display(), err))?;
+    let baseline = bench::parse_baseline(&text)?;
+    let mut report = String::new();
+    let mut regressions = 0;
+    for c in bench::compare(&measurements, &baseline) {
+        let regressed = c.regressed(options.threshold);
+        regressions += regressed as usize;
+        let _ = writeln!(report, "Day {:02} {}: {:.2?} -> {:.2?} ({:+.1}%){}",
+                         c.day, c.stage, c.baseline, c.current, c.change(),
+                         if regressed { "  REGRESSION" } else { "" });
+    }
+    eprint!("{}", report);
+    match regressions {
+        0 => Ok(()),
+        n => Err(format!("{} stage(s) regressed by more than {}%", n, options.threshold).into()),
+    }
+}
+
+#[cfg(test)]
+mod aoc_tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn parses_run_commands() {
+        let run = |days: Vec<u32>, parts: Vec<u32>| Command::Run { days, parts, source: Source::Default };
+        assert_eq!(Ok(run(vec![3], vec![2])), parse_args(&args("run --day 3 --part 2")));
+        assert_eq!(Ok(run(vec![3], vec![1, 2])), parse_args(&args("run --day 3")));
+        assert_eq!(Ok(run(vec![], vec![1, 2])), parse_args(&args("run --all")));
+        assert_eq!(Ok(Command::List), parse_args(&args("list")));
+    }
+
+    #[test]
+    fn parses_check_commands() {
+        assert_eq!(Ok(Command::Check { days: vec![], parts: vec![1, 2] }), parse_args(&args("check")));
+        assert_eq!(Ok(Command::Check { days: vec![5], parts: vec![2] }), parse_args(&args("check --day 5 --part 2")));
+    }
+
+    #[test]
+    fn takes_an_input_source_for_a_single_day() {
+        let source = |line| match parse_args(&args(line)) {
+            Ok(Command::Run { source, .. }) => Ok(source),
+            other => Err(format!("{:?}", other)),
+        };
+        assert_eq!(Ok(Source::File("in.txt".into())), source("run --day 3 in.txt --part 1"));
+        assert_eq!(Ok(Source::Stdin), source("run --day 3 -"));
+        assert_eq!(Ok(Source::Inline("1-9".to_string())), source("run --day 4 --input 1-9"));
+        assert!(source("run --all in.txt").is_err());
+    }
+
+    #[test]
+    fn parses_bench_options() {
+        let options = |line| match parse_args(&args(line)) {
+            Ok(Command::Bench { options, .. }) => Ok(options),
+            other => Err(format!("{:?}", other)),
+        };
+        assert_eq!(Ok(BenchOptions::default()), options("bench --all"));
+        let expected = BenchOptions {
+            samples: 3,
+            format: Format::Csv,
+            baseline: Some("base.csv".into()),
+            threshold: 5.0,
+            ..BenchOptions::default()
+        };
+        assert_eq!(Ok(expected), options("bench --day 2 --samples 3 --format csv --baseline base.csv --threshold 5"));
+        assert!(options("bench --all --format xml").is_err());
+        assert_eq!(Err("--output needs a value".to_string()), parse_args(&args("bench --all --output")));
+        assert!(options("bench --all in.txt").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_commands() {
+        assert!(parse_args(&args("run")).is_err());
+        assert!(parse_args(&args("run --all --day 2")).is_err());
+        assert_eq!(Err("could not parse >x<".to_string()), parse_args(&args("run --day x")));
+        assert_eq!(Err("--part needs a value".to_string()), parse_args(&args("run --day 1 --part")));
+        assert!(parse_args(&args("jump")).is_err());
+    }
+
+    #[test]
+    fn registers_each_day_once_in_order() {
+        let numbers = DAYS.iter().map(|day| day.number).collect::<Vec<u32>>();
+        assert_eq!((1..=DAYS.len() as u32).collect::<Vec<u32>>(), numbers);
+        assert!(find(6).is_err());
+    }
+
+    #[test]
+    fn every_day_parses_its_own_input() {
+        for day in DAYS {
+            let input = day.load_input().unwrap();
+            assert!(day.parse(&input).is_ok(), "{}", day.title());
+        }
+    }
+
+    #[test]
+    fn every_answers_file_parses() {
+        for day in DAYS {
+            assert!(day.known_answers().is_ok_and(|known| !known.is_empty()), "{}", day.title());
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use aoc_core::{Day, Solution, Solver};
+
+pub const DAY: Day = Day {
+    number: 1,
+    dir: env!("CARGO_MANIFEST_DIR"),
+    input: "input.txt",
+    expects: "module masses, one per line",
+    solver: Solver::of::<Day01>(),
+};
+
+pub struct Day01;
+
+impl Solution for Day01 {
+    type Input = Vec<i32>;
+
+    fn parse(input: &str) -> Result<Vec<i32>, Box<dyn Error>> {
+        Ok(parse(input)?)
+    }
+
+    fn part_1(masses: &Vec<i32>) -> Result<impl Display, Box<dyn Error>> {
+        Ok(part_1(masses))
+    }
+
+    fn part_2(masses: &Vec<i32>) -> Result<impl Display, Box<dyn Error>> {
+        Ok(part_2(masses))
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<i32>, String> {
+    input.lines()
+        .map(|line| i32::from_str(line).map_err(|_| format!("could not parse >{}<", line)))
+        .collect::<Result<Vec<i32>, String>>()
+}
+
+fn part_1(input_data: &[i32]) -> i32 {
+    input_data.iter()
+        .map(|n| (n / 3) - 2)
+        .sum()
+}
+
+fn part_2(input_data: &[i32]) -> i32 {
+    input_data.iter()
+        .map(|&n| {
+            let mut total = 0;
+            let mut current = n;
+            while current > 0 {
+                current = i32::max(0, (current / 3) - 2);
+                total += current;
+            }
+            total
+        })
+        .sum()
+}
@@ -0,0 +1,286 @@
+//! Differential fuzzing of `IComp` against the interpreter this day
+//! started with (see `original`): a straight-line loop over add,
+//! multiply and halt. Both should leave memory in the same state for
+//! every program built from opcodes 1, 2 and 99, so random programs are
+//! run through each and any disagreement is shrunk to a small reproducer
+//! and saved under `fuzz/regressions`, where the tests replay it.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::{Path, PathBuf};
+
+use intcode::{Cont, IComp};
+
+use crate::original;
+
+/// Instructions `IComp` may run before the program is reported as not
+/// halting. The original interpreter never jumps, so it always stops.
+pub const STEP_LIMIT: usize = 1000;
+
+/// xorshift64*, which is plenty for generating test programs.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// How a run ended, with memory as it was left.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Outcome {
+    Halted(Vec<i64>),
+    StepLimit(Vec<i64>),
+    /// The program left the subset both interpreters define: an unknown
+    /// opcode or an address outside the image.
+    Invalid,
+}
+
+/// Runs the original day 2 interpreter. It works on unsigned words and
+/// panics outside its subset, so negative words and panics both make the
+/// program `Outcome::Invalid`.
+pub fn reference(program: Vec<i64>) -> Outcome {
+    let program = match program.into_iter().map(|n| usize::try_from(n).ok()).collect::<Option<Vec<usize>>>() {
+        Some(program) => program,
+        None => return Outcome::Invalid,
+    };
+    match panic::catch_unwind(|| original::execute(program)) {
+        Ok(memory) => Outcome::Halted(memory.into_iter().map(|n| n as i64).collect()),
+        Err(_) => Outcome::Invalid,
+    }
+}
+
+/// Runs `IComp` one instruction at a time, up to `step_limit`.
+pub fn icomp(program: Vec<i64>, step_limit: usize) -> Outcome {
+    let mut comp = IComp::from_program(program);
+    for _ in 0..step_limit {
+        match comp.step() {
+            Ok(_) if comp.exec_state == Cont::Halt => return Outcome::Halted(comp.program),
+            Ok(_) => {},
+            Err(_) => return Outcome::Invalid,
+        }
+    }
+    Outcome::StepLimit(comp.program)
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Mismatch {
+    pub program: Vec<i64>,
+    pub reference: Outcome,
+    pub icomp: Outcome,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "program:   {}", to_source(&self.program))?;
+        writeln!(f, "reference: {:?}", self.reference)?;
+        write!(f, "icomp:     {:?}", self.icomp)
+    }
+}
+
+/// Runs both interpreters, returning how they disagree. Programs the
+/// reference rejects are outside the subset and never disagree.
+pub fn compare(program: &[i64]) -> Option<Mismatch> {
+    let reference = reference(program.to_vec());
+    if reference == Outcome::Invalid {
+        return None;
+    }
+    let icomp = icomp(program.to_vec(), STEP_LIMIT);
+    if reference == icomp {
+        return None;
+    }
+    Some(Mismatch { program: program.to_vec(), reference, icomp })
+}
+
+/// A random program of add and multiply instructions ending in a halt,
+/// followed by a few data cells. Every operand is an address inside the
+/// image. Most writes go to the data cells; the rest may land on code
+/// and rewrite it, which usually takes the program out of the subset.
+pub fn generate(rng: &mut Rng) -> Vec<i64> {
+    let instructions = 1 + rng.below(8);
+    let code = instructions * 4 + 1;
+    let len = code + 1 + rng.below(6);
+    let mut program = Vec::with_capacity(len);
+    for _ in 0..instructions {
+        program.push([1, 2][rng.below(2)]);
+        program.push(rng.below(len) as i64);
+        program.push(rng.below(len) as i64);
+        let dest = match rng.below(4) {
+            0 => rng.below(len),
+            _ => code + rng.below(len - code),
+        };
+        program.push(dest as i64);
+    }
+    program.push(99);
+    while program.len() < len {
+        program.push([0, 1, 2, 99, rng.below(100) as i64][rng.below(5)]);
+    }
+    program
+}
+
+/// Repeatedly drops words and lowers values for as long as `fails` still
+/// holds, returning a program where no single step of either kind keeps
+/// it failing.
+pub fn shrink(mut program: Vec<i64>, fails: impl Fn(&[i64]) -> bool) -> Vec<i64> {
+    let mut progress = true;
+    while progress {
+        progress = false;
+        for chunk in [4, 1] {
+            let mut i = 0;
+            while i + chunk <= program.len() {
+                let mut candidate = program.clone();
+                candidate.drain(i..i + chunk);
+                if !candidate.is_empty() && fails(&candidate) {
+                    program = candidate;
+                    progress = true;
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        for i in 0..program.len() {
+            for smaller in [0, 1, program[i] / 2, program[i].saturating_sub(1)] {
+                if smaller.unsigned_abs() >= program[i].unsigned_abs() {
+                    continue;
+                }
+                let mut candidate = program.clone();
+                candidate[i] = smaller;
+                if fails(&candidate) {
+                    program = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+    }
+    program
+}
+
+/// The result of a fuzzing session.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Report {
+    pub runs: usize,
+    /// Programs that rewrote themselves out of the subset.
+    pub invalid: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// Compares `runs` generated programs, shrinking each mismatch found.
+/// Mismatches that shrink to the same program are reported once.
+pub fn fuzz(seed: u64, runs: usize) -> Report {
+    let mut rng = Rng::new(seed);
+    let mut report = Report { runs, ..Report::default() };
+    for _ in 0..runs {
+        let program = generate(&mut rng);
+        if reference(program.clone()) == Outcome::Invalid {
+            report.invalid += 1;
+        } else if compare(&program).is_some() {
+            let minimal = shrink(program, |p| compare(p).is_some());
+            if report.mismatches.iter().all(|m| m.program != minimal) {
+                report.mismatches.extend(compare(&minimal));
+            }
+        }
+    }
+    report
+}
+
+pub fn regressions_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz").join("regressions")
+}
+
+fn to_source(program: &[i64]) -> String {
+    program.iter().map(|n| n.to_string()).collect::<Vec<String>>().join(",")
+}
+
+/// Writes a reproducer to the regressions directory, named after its
+/// contents so the same program is only saved once.
+pub fn save(dir: &Path, program: &[i64]) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let hash = program.iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |h, &n| (h ^ n as u64).wrapping_mul(0x100_0000_01b3));
+    let path = dir.join(format!("{:016x}.txt", hash));
+    fs::write(&path, to_source(program) + "\n")?;
+    Ok(path)
+}
+
+/// Every saved reproducer, sorted by file name.
+pub fn load(dir: &Path) -> io::Result<Vec<(PathBuf, Vec<i64>)>> {
+    let mut paths = match fs::read_dir(dir) {
+        Ok(entries) => entries.map(|entry| entry.map(|e| e.path())).collect::<io::Result<Vec<PathBuf>>>()?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "txt"));
+    paths.sort();
+    paths.into_iter()
+        .map(|path| {
+            let program = fs::read_to_string(&path)?
+                .trim()
+                .split(',')
+                .map(|n| n.parse().map_err(|_| io::Error::other(format!("could not parse >{}<", n))))
+                .collect::<io::Result<Vec<i64>>>()?;
+            Ok((path, program))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod fuzz_tests {
+    use super::*;
+    use aoc_core::scratch::Scratch;
+
+    #[test]
+    fn interpreters_agree_on_generated_programs() {
+        let report = fuzz(2019, 5000);
+        let mismatches = report.mismatches.iter().map(|m| m.to_string()).collect::<Vec<String>>();
+        assert!(mismatches.is_empty(), "\n{}", mismatches.join("\n\n"));
+        assert!(report.invalid < report.runs / 2, "{} of {} programs were invalid", report.invalid, report.runs);
+    }
+
+    #[test]
+    fn saved_regressions_still_agree() {
+        let saved = load(&regressions_dir()).expect("regressions are readable");
+        assert!(!saved.is_empty());
+        for (path, program) in saved {
+            assert_eq!(None, compare(&program), "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn reference_rejects_what_day_2_never_defined() {
+        assert_eq!(Outcome::Invalid, reference(vec![1, 0, 0, 7, 99]));
+        assert_eq!(Outcome::Invalid, reference(vec![101, 0, 0, 0, 99]));
+        assert_eq!(Outcome::Invalid, reference(vec![1, 0, 0, 0, -99]));
+        assert_eq!(Outcome::Halted(vec![2, 0, 0, 0, 99]), reference(vec![1, 0, 0, 0, 99]));
+    }
+
+    #[test]
+    fn shrinks_to_a_minimal_failing_program() {
+        let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        let minimal = shrink(program, |p| p.iter().any(|&n| n >= 40));
+        assert_eq!(vec![40], minimal);
+    }
+
+    #[test]
+    fn saves_and_loads_reproducers() {
+        let scratch = Scratch::new("day-02-fuzz");
+        let dir = scratch.join("reproducers");
+        let path = save(&dir, &[1, 0, 0, 0, 99]).unwrap();
+        assert_eq!(path, save(&dir, &[1, 0, 0, 0, 99]).unwrap());
+        assert_eq!(vec![(path, vec![1, 0, 0, 0, 99])], load(&dir).unwrap());
+    }
+}
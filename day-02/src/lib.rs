@@ -0,0 +1,174 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use aoc_core::{Day, Solution, Solver};
+use intcode::{IComp, IcompError};
+
+pub mod fuzz;
+pub mod original;
+pub mod symbolic;
+
+pub const DAY: Day = Day {
+    number: 2,
+    dir: env!("CARGO_MANIFEST_DIR"),
+    input: "input.txt",
+    expects: "an Intcode program of comma-separated integers",
+    solver: Solver::of::<Day02>(),
+};
+
+pub struct Day02;
+
+impl Solution for Day02 {
+    type Input = Vec<i64>;
+
+    fn parse(input: &str) -> Result<Vec<i64>, Box<dyn Error>> {
+        Ok(parse(input)?)
+    }
+
+    fn part_1(program: &Vec<i64>) -> Result<impl Display, Box<dyn Error>> {
+        Ok(part_1(program)?)
+    }
+
+    fn part_2(program: &Vec<i64>) -> Result<impl Display, Box<dyn Error>> {
+        // The solver only models add, multiply and halt in position mode;
+        // anything else it cannot follow is left to the machine.
+        let answer = match part_2_symbolic(program) {
+            Ok(answer) => answer,
+            Err(_) => part_2(program)?,
+        };
+        let answer = answer.ok_or("Did not find solution")?;
+        Ok(answer)
+    }
+}
+
+pub fn parse(input: &str) -> Result<Vec<i64>, String> {
+    input.trim()
+        .split(',')
+        .map(|n| i64::from_str(n).map_err(|_| format!("could not parse >{}<", n)))
+        .collect::<Result<Vec<i64>, String>>()
+}
+
+pub fn part_1(data: &[i64]) -> Result<i64, IcompError> {
+    const POS_1: i64 = 12;
+    const POS_2: i64 = 2;
+    let mut v = data.to_vec();
+    v[1] = POS_1;
+    v[2] = POS_2;
+    let result = execute(v)?;
+    Ok(result[0])
+}
+
+pub const TARGET: i64 = 19690720;
+
+pub fn part_2(data: &[i64]) -> Result<Option<i64>, IcompError> {
+    for noun in 0..100 {
+        for verb in 0..100 {
+            let mut v = data.to_vec();
+            v[1] = noun;
+            v[2] = verb;
+            let result = execute(v)?;
+            if result[0] == TARGET {
+                return Ok(Some(100 * noun + verb));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Solves for `TARGET` without running the program once per pair.
+pub fn part_2_symbolic(data: &[i64]) -> Result<Option<i64>, symbolic::SymError> {
+    let poly = symbolic::Poly::from_expr(&symbolic::run(data)?)?;
+    let pairs = symbolic::solve(&poly, TARGET)?;
+    Ok(pairs.first().map(|(noun, verb)| 100 * noun + verb))
+}
+
+pub fn execute(program: Vec<i64>) -> Result<Vec<i64>, IcompError> {
+    let mut comp = IComp::from_program(program);
+    comp.execute()?;
+    Ok(comp.program)
+}
+
+#[cfg(test)]
+mod day_02_tests {
+    use super::*;
+
+    #[test]
+    fn executes_an_add_instruction() {
+        let program = vec![1, 0, 0, 0, 99];
+        let output = execute(program).unwrap();
+        assert_eq!(vec![2, 0, 0, 0, 99], output);
+    }
+
+    #[test]
+    fn executes_multiply_instructions() {
+        let program = vec![2,3,0,3,99];
+        let output = execute(program).unwrap();
+        assert_eq!(vec![2, 3, 0, 6, 99], output);
+    }
+
+    #[test]
+    fn can_store_a_number_in_last_pos() {
+        let program = vec![2, 4, 4, 5, 99, 0];
+        let output = execute(program).unwrap();
+        assert_eq!(vec![2, 4, 4, 5, 99, 9801], output);
+    }
+
+    #[test]
+    fn halts_immeditately_on_opcode_99() {
+        let program = vec![99, 0, 0, 0, 99];
+        let output = execute(program).unwrap();
+        assert_eq!(vec![99, 0, 0, 0, 99], output);
+        
+    }
+
+    #[test]
+    fn can_execute_two_sequential_operations() {
+        let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
+        let output = execute(program).unwrap();
+        assert_eq!(vec![30, 1, 1, 4, 2, 5, 6, 0, 99], output);
+    }
+
+    #[test]
+    fn can_solve_the_example_problem() {
+        let program = vec![1,9,10,3,2,3,11,0,99,30,40,50];
+        let output = execute(program).unwrap();
+        assert_eq!(vec![3500,9,10,70,2,3,11,0,99,30,40,50], output);
+    }
+
+    #[test]
+    fn reports_programs_that_fault() {
+        let err = Day02::part_1(&vec![1, 0, 0, 0, 42]).err().unwrap();
+        assert_eq!("unknown opcode at iptr 4 (opcode value 42)", err.to_string());
+        assert!(Day02::part_2(&vec![1, 0, 0, 0, 42]).is_err());
+    }
+
+    #[test]
+    fn symbolic_solver_agrees_with_brute_force() {
+        let program = vec![1, 0, 0, 3, 2, 1, 13, 0, 1, 0, 2, 0, 99, 198896];
+        assert_eq!(Ok(Some(9916)), part_2(&program));
+        assert_eq!(Ok(Some(9916)), part_2_symbolic(&program));
+    }
+
+    #[test]
+    fn falls_back_to_brute_force_when_the_solver_cannot_follow() {
+        // position 0 = (noun + 3) * 2^62 + 19690720, which only hits the
+        // target once the machine wraps, at noun = 1.
+        let program = vec![
+            1, 0, 0, 3, 1, 1, 17, 1, 2, 1, 18, 0, 1, 0, 19, 0, 99, 3, 1 << 62, TARGET
+        ];
+        assert_eq!(Err(symbolic::SymError::Wraps), part_2_symbolic(&program));
+        assert_eq!("100", Day02::part_2(&program).unwrap().to_string());
+    }
+
+    #[test]
+    fn brute_force_runs_programs_using_parameter_modes() {
+        // position 0 = mem[noun + verb] + the last cell, and cells past
+        // the image read as 0.
+        let program = parse("1101,0,0,5,1,5,9,0,99,19690720").unwrap();
+        assert!(matches!(part_2_symbolic(&program), Err(symbolic::SymError::UnknownOpcode { .. })));
+        assert_eq!("1", Day02::part_2(&program).unwrap().to_string());
+        let unsolvable = parse("1101,0,0,5,1,5,9,0,99,19690710").unwrap();
+        assert_eq!("Did not find solution", Day02::part_2(&unsolvable).err().unwrap().to_string());
+    }
+}
@@ -1,137 +1,78 @@
-use std::io::Read;
-use std::fs::File;
 use std::str::FromStr;
 
-fn main() -> std::io::Result<()> {
-    let mut file = File::open("input.txt")?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
+use aoc_core::{Solution, Source};
+use day_02::{fuzz, symbolic, Day02, DAY, TARGET};
 
-    let input_data = contents.trim()
-        .split(',')
-        .map(|n| usize::from_str(n).expect("Failed to parse string"))
-        .collect::<Vec<usize>>();
-
-    let answer_1 = part_1(&input_data);
-    println!("Day 02; Part 1 = {:?}", answer_1);
-    
-    let answer_2 = part_2(&input_data).expect("Did not find solution");
-    println!("Day 02; Part 2 = {}", answer_2);
-    Ok(())
-}
-
-#[derive(PartialEq)]
-enum Cont {
-    Halt,
-    Continue,
-}
-
-fn part_1(data: &[usize]) -> usize {
-    const POS_1: usize = 12;
-    const POS_2: usize = 2;
-    let mut v = data.to_vec();
-    v[1] = POS_1;
-    v[2] = POS_2;
-    let result = execute(v);
-    result[0]
+fn main() {
+    aoc_core::report(try_main());
 }
 
-fn part_2(data: &[usize]) -> Option<usize> {
-    const TARGET: usize = 19690720;
-    for noun in 0..100 {
-        for verb in 0..100 {
-            let mut v = data.to_vec();
-            v[1] = noun;
-            v[2] = verb;
-            let result = execute(v);
-            if result[0] == TARGET {
-                return Some(100 * noun + verb);
-            }
-        }
+fn try_main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let source = Source::from_args(&args, &["--runs", "--seed", "--target", "--part"])?;
+    if args.iter().any(|arg| arg == "--fuzz") {
+        let runs = flag_value(&args, "--runs", 10_000)?;
+        let seed = flag_value(&args, "--seed", 2019)?;
+        return Ok(run_fuzzer(seed, runs)?);
     }
-    None
-}
 
-fn bin_op<F>(mut program: Vec<usize>, op_idx: usize, op: F) -> Vec<usize>
-where
-    F: Fn((usize,usize)) -> usize
-{
-    let (op1, op2, dest) = (program[op_idx + 1], program[op_idx + 2], program[op_idx + 3]);
-    program[dest] = op((program[op1], program[op2]));
-    program
-}
-    
-fn execute_from(mut program: Vec<usize>, op_idx: usize) -> (Vec<usize>, Cont) {
-    let opcode = program[op_idx];
-    let mut continuation = Cont::Continue;
-    match opcode {
-        1 => program = bin_op(program, op_idx, |(m, n)| m + n),
-        2 => program = bin_op(program, op_idx, |(m, n)| m * n),
-        99 => continuation = Cont::Halt,
-        _ => unreachable!(),
+    if args.iter().any(|arg| arg == "--symbolic") {
+        let target = flag_value(&args, "--target", TARGET)?;
+        let input_data = Day02::parse(&DAY.read_input(&source)?)?;
+        return Ok(solve_symbolically(&input_data, target)?);
     }
-    (program, continuation)
-}
 
-fn execute(program: Vec<usize>) -> Vec<usize> {
-    const CHUNK_SIZE: usize = 4;
-    let mut cont = Cont::Continue;
-    let mut output = program;
-    let mut op_idx = 0;
-    while cont != Cont::Halt {
-        let (o, c) = execute_from(output, op_idx);
-        output = o;
-        cont = c;
-        op_idx += CHUNK_SIZE;
-    }
-    output
+    aoc_core::run(&DAY, &source, &aoc_core::parts_from_args(&args)?)
 }
 
-#[cfg(test)]
-mod day_02_tests {
-    use super::*;
-
-    #[test]
-    fn executes_an_add_instruction() {
-        let program = vec![1, 0, 0, 0, 99];
-        let output = execute(program);
-        assert_eq!(vec![2, 0, 0, 0, 99], output);
+fn solve_symbolically(data: &[i64], target: i64) -> Result<(), symbolic::SymError> {
+    let expr = symbolic::run(data)?;
+    println!("position 0 = {}", expr);
+    let poly = symbolic::Poly::from_expr(&expr)?;
+    println!("           = {}", poly);
+    let pairs = symbolic::solve(&poly, target)?;
+    if pairs.is_empty() {
+        println!("no noun and verb in {:?} give {}", symbolic::RANGE, target);
     }
-
-    #[test]
-    fn executes_multiply_instructions() {
-        let program = vec![2,3,0,3,99];
-        let output = execute(program);
-        assert_eq!(vec![2, 3, 0, 6, 99], output);
+    for (noun, verb) in pairs {
+        println!("noun = {}, verb = {} (answer {})", noun, verb, 100 * noun + verb);
     }
+    Ok(())
+}
 
-    #[test]
-    fn can_store_a_number_in_last_pos() {
-        let program = vec![2, 4, 4, 5, 99, 0];
-        let output = execute(program);
-        assert_eq!(vec![2, 4, 4, 5, 99, 9801], output);
+fn flag_value<T: FromStr>(args: &[String], flag: &str, default: T) -> Result<T, String> {
+    match args.iter().position(|arg| arg == flag) {
+        Some(i) => {
+            let value = args.get(i + 1).ok_or(format!("{} needs a value", flag))?;
+            value.parse().map_err(|_| format!("could not parse >{}<", value))
+        },
+        None => Ok(default),
     }
+}
 
-    #[test]
-    fn halts_immeditately_on_opcode_99() {
-        let program = vec![99, 0, 0, 0, 99];
-        let output = execute(program);
-        assert_eq!(vec![99, 0, 0, 0, 99], output);
-        
+/// Replays the saved regressions, then compares `IComp` against the
+/// original interpreter on random programs, saving a shrunk reproducer
+/// for every disagreement.
+fn run_fuzzer(seed: u64, runs: usize) -> std::io::Result<()> {
+    // The original interpreter panics on every program that leaves its
+    // subset; those are counted below rather than printed.
+    std::panic::set_hook(Box::new(|_| {}));
+    let saved = fuzz::load(&fuzz::regressions_dir())?;
+    let failing = saved.iter().filter(|(_, program)| fuzz::compare(program).is_some());
+    for (path, _) in failing {
+        println!("regression {} still mismatches", path.display());
     }
-
-    #[test]
-    fn can_execute_two_sequential_operations() {
-        let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
-        let output = execute(program);
-        assert_eq!(vec![30, 1, 1, 4, 2, 5, 6, 0, 99], output);
+    let report = fuzz::fuzz(seed, runs);
+    println!(
+        "{} programs from seed {}: {} left the subset, {} mismatched",
+        report.runs, seed, report.invalid, report.mismatches.len()
+    );
+    for mismatch in report.mismatches.iter() {
+        let path = fuzz::save(&fuzz::regressions_dir(), &mismatch.program)?;
+        println!("\n{}\nsaved to {}", mismatch, path.display());
     }
-
-    #[test]
-    fn can_solve_the_example_problem() {
-        let program = vec![1,9,10,3,2,3,11,0,99,30,40,50];
-        let output = execute(program);
-        assert_eq!(vec![3500,9,10,70,2,3,11,0,99,30,40,50], output);
+    if !report.mismatches.is_empty() {
+        std::process::exit(1);
     }
-
+    Ok(())
 }
@@ -0,0 +1,44 @@
+//! The interpreter this day was first solved with, kept as it was so the
+//! fuzzer has an independent oracle for `IComp`. It only knows add,
+//! multiply and halt, and panics on anything else.
+
+#[derive(PartialEq)]
+enum Cont {
+    Halt,
+    Continue,
+}
+
+fn bin_op<F>(mut program: Vec<usize>, op_idx: usize, op: F) -> Vec<usize>
+where
+    F: Fn((usize,usize)) -> usize
+{
+    let (op1, op2, dest) = (program[op_idx + 1], program[op_idx + 2], program[op_idx + 3]);
+    program[dest] = op((program[op1], program[op2]));
+    program
+}
+    
+fn execute_from(mut program: Vec<usize>, op_idx: usize) -> (Vec<usize>, Cont) {
+    let opcode = program[op_idx];
+    let mut continuation = Cont::Continue;
+    match opcode {
+        1 => program = bin_op(program, op_idx, |(m, n)| m + n),
+        2 => program = bin_op(program, op_idx, |(m, n)| m * n),
+        99 => continuation = Cont::Halt,
+        _ => unreachable!(),
+    }
+    (program, continuation)
+}
+
+pub fn execute(program: Vec<usize>) -> Vec<usize> {
+    const CHUNK_SIZE: usize = 4;
+    let mut cont = Cont::Continue;
+    let mut output = program;
+    let mut op_idx = 0;
+    while cont != Cont::Halt {
+        let (o, c) = execute_from(output, op_idx);
+        output = o;
+        cont = c;
+        op_idx += CHUNK_SIZE;
+    }
+    output
+}
@@ -0,0 +1,352 @@
+//! Runs the gravity assist program with the noun and verb (positions 1
+//! and 2) held as unknowns, so the value left at position 0 comes out as
+//! an expression that can be solved for any target instead of searched.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+/// Nouns and verbs the puzzle allows.
+pub const RANGE: std::ops::RangeInclusive<i64> = 0..=99;
+
+#[derive(PartialEq, Debug)]
+pub enum Expr {
+    Const(i64),
+    Noun,
+    Verb,
+    Add(Rc<Expr>, Rc<Expr>),
+    Mul(Rc<Expr>, Rc<Expr>),
+    /// The value at an address that itself depends on the unknowns.
+    Load(Rc<Expr>),
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Const(n) => write!(f, "{}", n),
+            Expr::Noun => write!(f, "noun"),
+            Expr::Verb => write!(f, "verb"),
+            Expr::Add(a, b) => write!(f, "({} + {})", a, b),
+            Expr::Mul(a, b) => write!(f, "{} * {}", a, b),
+            Expr::Load(addr) => write!(f, "mem[{}]", addr),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SymError {
+    UnknownOpcode { addr: usize, opcode: i64 },
+    /// An opcode or write destination depends on the unknowns.
+    SymbolicControl(usize),
+    OutOfBounds(i64),
+    /// Position 0 depends on a value read through an unknown address.
+    OpaqueResult,
+    Overflow,
+    /// Position 0 leaves the `i64` range for some noun and verb, where the
+    /// machine would wrap around instead.
+    Wraps,
+}
+
+impl fmt::Display for SymError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymError::UnknownOpcode { addr, opcode } => write!(f, "unknown opcode {} at {}", opcode, addr),
+            SymError::SymbolicControl(addr) => {
+                write!(f, "instruction at {} depends on the noun or verb", addr)
+            },
+            SymError::OutOfBounds(addr) => write!(f, "address {} is out of bounds", addr),
+            SymError::OpaqueResult => write!(f, "position 0 depends on memory at an unknown address"),
+            SymError::Overflow => write!(f, "coefficient overflow"),
+            SymError::Wraps => write!(f, "position 0 wraps around for some noun and verb"),
+        }
+    }
+}
+
+impl std::error::Error for SymError {}
+
+fn add(a: &Rc<Expr>, b: &Rc<Expr>) -> Rc<Expr> {
+    match (&**a, &**b) {
+        (Expr::Const(0), _) => b.clone(),
+        (_, Expr::Const(0)) => a.clone(),
+        (Expr::Const(x), Expr::Const(y)) => Rc::new(Expr::Const(x.wrapping_add(*y))),
+        _ => Rc::new(Expr::Add(a.clone(), b.clone())),
+    }
+}
+
+fn mul(a: &Rc<Expr>, b: &Rc<Expr>) -> Rc<Expr> {
+    match (&**a, &**b) {
+        (Expr::Const(0), _) | (_, Expr::Const(0)) => Rc::new(Expr::Const(0)),
+        (Expr::Const(1), _) => b.clone(),
+        (_, Expr::Const(1)) => a.clone(),
+        (Expr::Const(x), Expr::Const(y)) => Rc::new(Expr::Const(x.wrapping_mul(*y))),
+        _ => Rc::new(Expr::Mul(a.clone(), b.clone())),
+    }
+}
+
+fn cell(mem: &[Rc<Expr>], addr: i64) -> Result<usize, SymError> {
+    usize::try_from(addr).ok()
+        .filter(|&a| a < mem.len())
+        .ok_or(SymError::OutOfBounds(addr))
+}
+
+fn concrete(mem: &[Rc<Expr>], addr: usize) -> Result<Option<i64>, SymError> {
+    match mem.get(addr).map(|e| &**e) {
+        Some(Expr::Const(n)) => Ok(Some(*n)),
+        Some(_) => Ok(None),
+        None => Err(SymError::OutOfBounds(addr as i64)),
+    }
+}
+
+/// Reads a position-mode operand. Reading through an unknown address
+/// cannot be resolved, so it yields an opaque `Load` instead.
+fn operand(mem: &[Rc<Expr>], param_addr: usize) -> Result<Rc<Expr>, SymError> {
+    match concrete(mem, param_addr)? {
+        Some(addr) => Ok(mem[cell(mem, addr)?].clone()),
+        None => Ok(Rc::new(Expr::Load(mem[param_addr].clone()))),
+    }
+}
+
+/// Runs the program with the day 2 instruction set (add, multiply, halt)
+/// and returns the expression left at position 0.
+pub fn run(program: &[i64]) -> Result<Rc<Expr>, SymError> {
+    let mut mem = program.iter().map(|&n| Rc::new(Expr::Const(n))).collect::<Vec<Rc<Expr>>>();
+    if mem.len() < 3 {
+        return Err(SymError::OutOfBounds(2));
+    }
+    mem[1] = Rc::new(Expr::Noun);
+    mem[2] = Rc::new(Expr::Verb);
+    let mut iptr = 0;
+    loop {
+        let opcode = concrete(&mem, iptr)?.ok_or(SymError::SymbolicControl(iptr))?;
+        let op: fn(&Rc<Expr>, &Rc<Expr>) -> Rc<Expr> = match opcode {
+            1 => add,
+            2 => mul,
+            99 => return Ok(mem[0].clone()),
+            _ => return Err(SymError::UnknownOpcode { addr: iptr, opcode }),
+        };
+        let a = operand(&mem, iptr + 1)?;
+        let b = operand(&mem, iptr + 2)?;
+        let dest = concrete(&mem, iptr + 3)?.ok_or(SymError::SymbolicControl(iptr))?;
+        let dest = cell(&mem, dest)?;
+        mem[dest] = op(&a, &b);
+        iptr += 4;
+    }
+}
+
+/// A polynomial in the noun and verb, keyed by (noun power, verb power).
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Poly(BTreeMap<(u32, u32), i128>);
+
+impl Poly {
+    fn term(powers: (u32, u32), coeff: i128) -> Self {
+        let mut poly = Poly::default();
+        if coeff != 0 {
+            poly.0.insert(powers, coeff);
+        }
+        poly
+    }
+
+    /// Adds `coeff` to the term with `powers` in place, dropping it if
+    /// it cancels out.
+    fn accumulate(&mut self, powers: (u32, u32), coeff: i128) -> Result<(), SymError> {
+        let sum = self.0.get(&powers).unwrap_or(&0).checked_add(coeff).ok_or(SymError::Overflow)?;
+        match sum {
+            0 => self.0.remove(&powers),
+            _ => self.0.insert(powers, sum),
+        };
+        Ok(())
+    }
+
+    fn add(&self, other: &Poly) -> Result<Poly, SymError> {
+        let mut sum = self.clone();
+        for (&powers, &coeff) in other.0.iter() {
+            sum.accumulate(powers, coeff)?;
+        }
+        Ok(sum)
+    }
+
+    fn mul(&self, other: &Poly) -> Result<Poly, SymError> {
+        let mut product = Poly::default();
+        for (&(n1, v1), &c1) in self.0.iter() {
+            for (&(n2, v2), &c2) in other.0.iter() {
+                let coeff = c1.checked_mul(c2).ok_or(SymError::Overflow)?;
+                let n = n1.checked_add(n2).ok_or(SymError::Overflow)?;
+                let v = v1.checked_add(v2).ok_or(SymError::Overflow)?;
+                product.accumulate((n, v), coeff)?;
+            }
+        }
+        Ok(product)
+    }
+
+    /// Converts an expression tree, sharing the work for subtrees that
+    /// appear more than once.
+    pub fn from_expr(expr: &Rc<Expr>) -> Result<Poly, SymError> {
+        Self::convert(expr, &mut HashMap::new())
+    }
+
+    fn convert(expr: &Rc<Expr>, seen: &mut HashMap<*const Expr, Poly>) -> Result<Poly, SymError> {
+        if let Some(poly) = seen.get(&Rc::as_ptr(expr)) {
+            return Ok(poly.clone());
+        }
+        let poly = match &**expr {
+            Expr::Const(n) => Poly::term((0, 0), *n as i128),
+            Expr::Noun => Poly::term((1, 0), 1),
+            Expr::Verb => Poly::term((0, 1), 1),
+            Expr::Add(a, b) => Self::convert(a, seen)?.add(&Self::convert(b, seen)?)?,
+            Expr::Mul(a, b) => Self::convert(a, seen)?.mul(&Self::convert(b, seen)?)?,
+            Expr::Load(_) => return Err(SymError::OpaqueResult),
+        };
+        seen.insert(Rc::as_ptr(expr), poly.clone());
+        Ok(poly)
+    }
+
+    /// The polynomial in the verb left once the noun is fixed, as
+    /// coefficients indexed by power.
+    fn with_noun(&self, noun: i64) -> Result<Vec<i128>, SymError> {
+        let mut coeffs = Vec::<i128>::new();
+        for (&(n, v), &c) in self.0.iter() {
+            let scale = (noun as i128).checked_pow(n).ok_or(SymError::Overflow)?;
+            let coeff = c.checked_mul(scale).ok_or(SymError::Overflow)?;
+            if coeffs.len() <= v as usize {
+                coeffs.resize(v as usize + 1, 0);
+            }
+            coeffs[v as usize] = coeffs[v as usize].checked_add(coeff).ok_or(SymError::Overflow)?;
+        }
+        while coeffs.last() == Some(&0) {
+            coeffs.pop();
+        }
+        Ok(coeffs)
+    }
+}
+
+impl fmt::Display for Poly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "0");
+        }
+        let power = |name: &str, p: u32| match p {
+            0 => None,
+            1 => Some(name.to_string()),
+            _ => Some(format!("{}^{}", name, p)),
+        };
+        let terms = self.0.iter().rev()
+            .map(|(&(n, v), &c)| {
+                let vars = [power("noun", n), power("verb", v)].into_iter().flatten().collect::<Vec<String>>();
+                match (c, vars.is_empty()) {
+                    (_, true) => c.to_string(),
+                    (1, false) => vars.join("*"),
+                    (-1, false) => format!("-{}", vars.join("*")),
+                    _ => format!("{}*{}", c, vars.join("*")),
+                }
+            })
+            .collect::<Vec<String>>();
+        write!(f, "{}", terms.join(" + ").replace("+ -", "- "))
+    }
+}
+
+fn eval(coeffs: &[i128], x: i128) -> Option<i128> {
+    coeffs.iter().rev().try_fold(0i128, |acc, &c| acc.checked_mul(x)?.checked_add(c))
+}
+
+/// Every (noun, verb) pair in `RANGE` for which `poly` equals `target`.
+/// With the noun fixed, a linear polynomial in the verb is solved by
+/// division; higher degrees are checked at each verb, which is still an
+/// exhaustive proof when nothing is found.
+///
+/// The polynomial is exact, so it only agrees with the machine's wrapping
+/// `i64` arithmetic while its value stays in that range; `Wraps` reports
+/// a program that leaves it.
+pub fn solve(poly: &Poly, target: i64) -> Result<Vec<(i64, i64)>, SymError> {
+    let mut pairs = Vec::new();
+    for noun in RANGE {
+        let mut coeffs = poly.with_noun(noun)?;
+        let values = RANGE.map(|verb| eval(&coeffs, verb as i128));
+        if values.map(|v| v.and_then(|v| i64::try_from(v).ok())).any(|v| v.is_none()) {
+            return Err(SymError::Wraps);
+        }
+        if coeffs.is_empty() {
+            coeffs.push(0);
+        }
+        coeffs[0] = coeffs[0].checked_sub(target as i128).ok_or(SymError::Overflow)?;
+        while coeffs.last() == Some(&0) {
+            coeffs.pop();
+        }
+        match coeffs.as_slice() {
+            [] => pairs.extend(RANGE.map(|verb| (noun, verb))),
+            [_] => {},
+            [c0, c1] => {
+                let verb = i64::try_from(-c0 / c1).ok().filter(|verb| RANGE.contains(verb));
+                if let (0, Some(verb)) = (c0 % c1, verb) {
+                    pairs.push((noun, verb));
+                }
+            },
+            _ => pairs.extend(RANGE.filter(|&verb| eval(&coeffs, verb as i128) == Some(0))
+                .map(|verb| (noun, verb))),
+        }
+    }
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod symbolic_tests {
+    use super::*;
+
+    // position 0 = 100 * noun + verb, after a dead write through the
+    // unknown addresses in the first instruction.
+    const LINEAR: [i64; 14] = [1, 0, 0, 3, 2, 1, 13, 0, 1, 0, 2, 0, 99, 100];
+
+    #[test]
+    fn builds_an_expression_for_position_0() {
+        let expr = run(&LINEAR).unwrap();
+        assert_eq!("(noun * 100 + verb)", expr.to_string());
+        assert_eq!("100*noun + verb", Poly::from_expr(&expr).unwrap().to_string());
+    }
+
+    #[test]
+    fn solves_a_linear_program() {
+        let poly = Poly::from_expr(&run(&LINEAR).unwrap()).unwrap();
+        assert_eq!(Ok(vec![(12, 34)]), solve(&poly, 1234));
+        assert_eq!(Ok(vec![]), solve(&poly, 10_000));
+    }
+
+    #[test]
+    fn reports_every_solution() {
+        let poly = Poly::from_expr(&run(&[1, 0, 0, 3, 2, 1, 2, 0, 99]).unwrap()).unwrap();
+        assert_eq!("noun*verb", poly.to_string());
+        assert_eq!(
+            Ok(vec![(1, 12), (2, 6), (3, 4), (4, 3), (6, 2), (12, 1)]),
+            solve(&poly, 12)
+        );
+        let square = Poly::from_expr(&run(&[1, 0, 0, 3, 2, 1, 1, 0, 99]).unwrap()).unwrap();
+        assert_eq!(100, solve(&square, 49).unwrap().len());
+    }
+
+    #[test]
+    fn rejects_results_read_through_unknown_addresses() {
+        let opaque = run(&[1, 0, 0, 0, 99]).unwrap();
+        assert_eq!("(mem[noun] + mem[verb])", opaque.to_string());
+        assert_eq!(Err(SymError::OpaqueResult), Poly::from_expr(&opaque));
+    }
+
+    #[test]
+    fn reports_exponents_too_large_to_represent() {
+        // The noun is squared in place 32 times, then added to the verb.
+        let mut program = vec![1, 0, 0, 3];
+        (0..32).for_each(|_| program.extend([2, 1, 1, 1]));
+        program.extend([1, 1, 2, 0, 99]);
+        assert_eq!(Err(SymError::Overflow), Poly::from_expr(&run(&program).unwrap()));
+    }
+
+    #[test]
+    fn reports_results_the_machine_would_wrap() {
+        // position 0 = noun * 2^62, which wraps to 0 from noun = 4 on.
+        let poly = Poly::from_expr(&run(&[1, 0, 0, 3, 2, 1, 9, 0, 99, 1 << 62]).unwrap()).unwrap();
+        assert_eq!(Err(SymError::Wraps), solve(&poly, 0));
+    }
+
+    #[test]
+    fn rejects_code_that_depends_on_the_unknowns() {
+        assert_eq!(Err(SymError::SymbolicControl(4)), run(&[1, 0, 0, 4, 0]));
+        assert_eq!(Err(SymError::SymbolicControl(4)), run(&[1, 0, 0, 7, 1, 5, 5, 0, 99]));
+    }
+}
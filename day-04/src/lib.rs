@@ -0,0 +1,146 @@
+#![allow(unused_variables)]
+#![allow(dead_code)]
+
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use aoc_core::{Day, Solution, Solver};
+
+pub const DAY: Day = Day {
+    number: 4,
+    dir: env!("CARGO_MANIFEST_DIR"),
+    input: "input.txt",
+    expects: "a password range such as 357253-892942",
+    solver: Solver::of::<Day04>(),
+};
+
+pub struct Day04;
+
+impl Solution for Day04 {
+    type Input = (u32, u32);
+
+    fn parse(input: &str) -> Result<(u32, u32), Box<dyn Error>> {
+        match parse_range(input.trim())? {
+            (start, end) if end > start => Ok((start, end)),
+            (start, end) => Err(format!("range {}-{} is empty", start, end).into()),
+        }
+    }
+
+    fn part_1(range: &(u32, u32)) -> Result<impl Display, Box<dyn Error>> {
+        Ok(part_1(*range))
+    }
+
+    fn part_2(range: &(u32, u32)) -> Result<impl Display, Box<dyn Error>> {
+        Ok(part_2(*range))
+    }
+}
+
+pub fn part_1((start, end): (u32, u32)) -> usize {
+    (start..=end)
+        .map(digits)
+        .filter(|ds| has_adjacent_matching_digits(ds))
+        .filter(|ds| is_non_decreasing(ds))
+        .count()
+}
+
+pub fn part_2((start, end): (u32, u32)) -> usize {
+    (start..=end)
+        .map(digits)
+        .filter(|ds| is_non_decreasing(ds))
+        .map(|ds| run_length_encode(&ds))
+        .filter(|rle| rle.iter().any(|(_, count)| *count == 2))
+        .count()
+}
+
+
+fn parse_range(s: &str) -> Result<(u32, u32), String> {
+    let range = s.split("-")
+        .map(|n| u32::from_str(n).map_err(|_| format!("could not parse >{}<", n)))
+        .collect::<Result<Vec<u32>, String>>()?;
+    match range[..] {
+        [start, end] => Ok((start, end)),
+        _ => Err(format!("could not parse >{}<", s)),
+    }
+}
+
+fn digits(n: u32) -> Vec<u32> {
+    n.to_string()
+        .chars()
+        .map(|c| u32::from_str(&c.to_string()).expect("Found non int char"))
+        .collect()
+}
+
+fn run_length_encode(ns: &[u32]) -> Vec<(u32, u32)> {
+    let mut run = 1;
+    let mut result = Vec::new();
+    for slice in ns.windows(2) {
+        if slice[0] == slice[1] {
+            run += 1;
+        } else {
+            result.push((slice[0], run));
+            run = 1;
+        }
+    }
+    result.push((*ns.last().expect("Digits are empty"), run));
+    result
+}
+
+fn has_adjacent_matching_digits(ns: &[u32]) -> bool {
+    ns.windows(2).any(|slice| slice[0] == slice[1])
+}
+
+fn is_non_decreasing(ns: &[u32]) -> bool {
+    ns.windows(2).all(|slice| slice[0] <= slice[1])
+}
+
+#[cfg(test)]
+mod day_04_tests {
+    use super::*;
+
+    #[test]
+    fn parse_range_parses_a_dash_separated_range() {
+        let range = "123-456";
+        assert_eq!(Ok((123, 456)), parse_range(range));
+    }
+
+    #[test]
+    fn rejects_malformed_and_empty_ranges() {
+        assert_eq!(Err("could not parse >4x<".to_string()), parse_range("123-4x"));
+        assert!(parse_range("1-2-3").is_err());
+        assert!(Day04::parse("456-123").is_err());
+    }
+
+    #[test]
+    fn has_adjacent_matching_digits_returns_false_if_n_has_none() {
+        let n = 123;
+        assert!(!has_adjacent_matching_digits(&digits(n)));
+    }
+
+    #[test]
+    fn has_adjacent_digits_returns_true_if_n_has_some() {
+        let n = 1223;
+        assert!(has_adjacent_matching_digits(&digits(n)));
+    }
+
+    #[test]
+    fn is_non_decreasing_returns_false_if_2_decreasing_elements() {
+        let n = 12324;
+        assert!(!is_non_decreasing(&digits(n)));
+    }
+
+    #[test]
+    fn is_non_decreasing_returns_true_if_no_2_decreasing_elements() {
+        let n = 12334;
+        assert!(is_non_decreasing(&digits(n)));
+    }
+
+    #[test]
+    fn can_run_length_encode_a_number() {
+        let n = 12233345;
+        assert_eq!(
+            vec![(1, 1), (2, 2), (3, 3), (4, 1), (5, 1)],
+            run_length_encode(&digits(n))
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use aoc_core::{Day, Solution, Solver};
+use intcode::{IComp, IcompError};
+
+pub const DAY: Day = Day {
+    number: 5,
+    dir: env!("CARGO_MANIFEST_DIR"),
+    input: "input.txt",
+    expects: "an Intcode program of comma-separated integers",
+    solver: Solver::of::<Day05>(),
+};
+
+pub struct Day05;
+
+impl Solution for Day05 {
+    type Input = Vec<i64>;
+
+    fn parse(input: &str) -> Result<Vec<i64>, Box<dyn Error>> {
+        Ok(parse_program(input.trim())?)
+    }
+
+    fn part_1(program: &Vec<i64>) -> Result<impl Display, Box<dyn Error>> {
+        Ok(part_1(program)?)
+    }
+
+    fn part_2(program: &Vec<i64>) -> Result<impl Display, Box<dyn Error>> {
+        Ok(part_2(program)?)
+    }
+}
+
+pub fn io_execution(comp: &mut IComp, input: i64) -> Result<String, IcompError> {
+    comp.push_input(input);
+    comp.execute()?;
+    Ok(comp.output.iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+pub fn parse_program(s: &str) -> Result<Vec<i64>, String> {
+    let parse_fail_msg = |n| {
+        format!("could not parse >{}<", n)
+    };
+    s.split(",")
+        .map(|n| i64::from_str(n).map_err(|_| parse_fail_msg(n)))
+        .collect::<Result<Vec<i64>, String>>()
+}
+
+pub fn part_1(program: &[i64]) -> Result<String, IcompError> {
+    let mut comp = IComp::from_program(program.to_vec());
+    io_execution(&mut comp, 1)
+}
+
+pub fn part_2(program: &[i64]) -> Result<String, IcompError> {
+    let mut comp = IComp::from_program(program.to_vec());
+    io_execution(&mut comp, 5)
+}
+
+#[cfg(test)]
+mod day_05_tests {
+    use super::*;
+    use intcode::{asm, Checked, Cont, Fault, Status};
+
+    fn assert_final_program_state_input_0(init: Vec<i64>, expected: Vec<i64>) {
+        let mut computer = IComp::from_program(init);
+        computer.push_input(0);
+        computer.execute().expect("program faulted");
+        assert_eq!(expected, computer.program);
+    }
+
+    #[test]
+    fn executes_an_add_instruction() {
+        assert_final_program_state_input_0(
+            vec![1, 0, 0, 0, 99],
+            vec![2, 0, 0, 0, 99]
+        );
+    }
+
+    #[test]
+    fn executes_multiply_instructions() {
+        assert_final_program_state_input_0(
+            vec![2,3,0,3,99],
+            vec![2, 3, 0, 6, 99]
+        );
+    }
+
+    #[test]
+    fn can_store_a_number_in_last_pos() {
+        assert_final_program_state_input_0(
+            vec![2, 4, 4, 5, 99, 0],
+            vec![2, 4, 4, 5, 99, 9801]
+        );        
+    }
+
+    #[test]
+    fn halts_immeditately_on_opcode_99() {
+        assert_final_program_state_input_0(
+            vec![99, 0, 0, 0, 99],
+            vec![99, 0, 0, 0, 99]
+        );
+    }
+
+    #[test]
+    fn can_execute_two_sequential_operations() {
+        assert_final_program_state_input_0(
+            vec![1, 1, 1, 4, 99, 5, 6, 0, 99],
+            vec![30, 1, 1, 4, 2, 5, 6, 0, 99]
+        );
+    }
+
+    #[test]
+    fn can_solve_the_example_problem() {
+        assert_final_program_state_input_0(
+            vec![1,9,10,3,2,3,11,0,99,30,40,50],
+            vec![3500,9,10,70,2,3,11,0,99,30,40,50]
+        );
+    }
+    
+    #[test]
+    fn should_execute_multiply_command_based_on_new_parameter_modes() {
+        assert_final_program_state_input_0(
+            vec![1002, 4, 3, 4, 33],
+            vec![1002, 4, 3, 4, 99]
+        );
+    }
+
+    #[test]
+    fn can_handle_programs_with_negative_values() {
+        assert_final_program_state_input_0(
+            vec![1101,100,-1,4,0],
+            vec![1101, 100, -1, 4, 99]
+        );
+    }
+
+    #[test]
+    fn pauses_when_input_queue_is_empty_and_resumes_later() {
+        let mut computer = IComp::from_program(vec![3, 0, 4, 0, 99]);
+        assert_eq!(Ok(Status::NeedsInput), computer.resume());
+        assert_eq!(0, computer.iptr);
+        computer.push_input(42);
+        assert_eq!(Ok(Status::Output(42)), computer.resume());
+        assert_eq!(Ok(Status::Halted), computer.resume());
+    }
+
+    #[test]
+    fn consumes_queued_inputs_in_order() {
+        let mut computer = IComp::from_program(vec![3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0]);
+        computer.push_input(3);
+        computer.push_input(4);
+        assert_eq!(Ok(Cont::Halt), computer.execute());
+        assert_eq!(vec![7], computer.output);
+    }
+
+    #[test]
+    fn execute_reports_when_blocked_on_input() {
+        let mut computer = IComp::from_program(vec![4, 0, 3, 0, 99]);
+        assert_eq!(Ok(Cont::NeedsInput), computer.execute());
+        assert_eq!(vec![4], computer.output);
+        computer.push_input(1);
+        assert_eq!(Ok(Cont::Halt), computer.execute());
+    }
+
+    fn run_to_halt(program: Vec<i64>) -> Vec<i64> {
+        let mut computer = IComp::from_program(program);
+        assert_eq!(Ok(Cont::Halt), computer.execute());
+        computer.output
+    }
+
+    #[test]
+    fn quine_outputs_a_copy_of_itself() {
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99
+        ];
+        assert_eq!(program.clone(), run_to_halt(program));
+    }
+
+    #[test]
+    fn can_output_a_sixteen_digit_number() {
+        let output = run_to_halt(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0]);
+        assert_eq!(16, output[0].to_string().len());
+    }
+
+    #[test]
+    fn can_output_a_large_immediate_value() {
+        assert_eq!(vec![1125899906842624], run_to_halt(vec![104, 1125899906842624, 99]));
+    }
+
+    #[test]
+    fn reads_past_the_loaded_image_as_zero() {
+        assert_eq!(vec![0], run_to_halt(vec![4, 1000, 99]));
+    }
+
+    #[test]
+    fn writes_past_the_loaded_image_grow_memory() {
+        let mut computer = IComp::from_program(vec![109, 10, 21101, 3, 4, 0, 99]);
+        computer.execute().expect("program faulted");
+        assert_eq!(11, computer.program.len());
+        assert_eq!(7, computer.program[10]);
+    }
+
+    fn fault_of(program: Vec<i64>) -> IcompError {
+        let mut computer = IComp::from_program(program);
+        computer.execute().expect_err("program should fault")
+    }
+
+    #[test]
+    fn reports_unknown_opcodes_with_their_location() {
+        let err = fault_of(vec![1101, 1, 1, 0, 42]);
+        assert_eq!(IcompError { iptr: 4, opval: 42, fault: Fault::UnknownOpcode }, err);
+    }
+
+    #[test]
+    fn reports_unknown_parameter_modes() {
+        let err = fault_of(vec![304, 0, 99]);
+        assert_eq!(IcompError { iptr: 0, opval: 304, fault: Fault::UnknownParamMode(3) }, err);
+    }
+
+    #[test]
+    fn reports_negative_addresses() {
+        let err = fault_of(vec![1, -1, 0, 0, 99]);
+        assert_eq!(Fault::NegativeAddress(-1), err.fault);
+    }
+
+    #[test]
+    fn reports_writes_in_immediate_mode() {
+        let err = fault_of(vec![10001, 0, 0, 0, 99]);
+        assert_eq!(Fault::ImmediateModeWrite, err.fault);
+    }
+
+    #[test]
+    fn reports_jumps_to_negative_addresses() {
+        let err = fault_of(vec![1105, 1, -7]);
+        assert_eq!(IcompError { iptr: 0, opval: 1105, fault: Fault::NegativeAddress(-7) }, err);
+    }
+
+    #[test]
+    fn reports_overflow_instead_of_panicking() {
+        let program = [1102, i64::MAX, 2, 0, 99].into_iter().map(Checked).collect();
+        let mut computer = IComp::from_words(program);
+        let err = computer.execute().expect_err("program should fault");
+        assert_eq!(Fault::Overflow, err.fault);
+    }
+
+    #[test]
+    fn plain_words_wrap_on_overflow() {
+        let mut computer = IComp::from_program(vec![1102, i64::MAX, 2, 0, 99]);
+        computer.execute().expect("program faulted");
+        assert_eq!(-2, computer.program[0]);
+    }
+
+    #[test]
+    fn error_message_names_the_instruction() {
+        let err = fault_of(vec![1101, 1, 1, 0, 42]);
+        assert_eq!("unknown opcode at iptr 4 (opcode value 42)", err.to_string());
+    }
+
+    #[test]
+    fn runs_an_assembled_self_modifying_program() {
+        let program = asm::assemble("
+                mul [patch], 3, [patch]
+        patch:  data 33             ; 33 * 3 = 99, so this becomes HLT
+        ").expect("valid source");
+        let mut computer = IComp::from_program(program);
+        assert_eq!(Ok(Cont::Halt), computer.execute());
+        assert_eq!(vec![1002, 4, 3, 4, 99], computer.program);
+    }
+}
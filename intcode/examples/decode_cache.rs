@@ -0,0 +1,53 @@
+//! Times programs with and without the decode cache.
+//!
+//!     cargo run --release --example decode_cache [program] [runs]
+//!
+//! The program defaults to the day 5 puzzle input, run for both of its
+//! system IDs. That program executes each instruction only a handful of
+//! times, so the countdown loop in `countdown.ic` is timed as well to show
+//! what the cache saves once instructions repeat.
+
+use std::fs;
+use std::time::{Duration, Instant};
+
+use intcode::{asm, IComp};
+
+const DAY_05: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../day-05/input.txt");
+
+fn time_runs(program: &[i64], inputs: &[i64], runs: u32, cache: bool) -> Duration {
+    let start = Instant::now();
+    for _ in 0..runs {
+        for &input in inputs {
+            let mut comp = IComp::from_program(program.to_vec());
+            comp.set_decode_cache(cache);
+            comp.push_input(input);
+            comp.execute().expect("program faulted");
+        }
+    }
+    start.elapsed()
+}
+
+fn compare(name: &str, program: &[i64], inputs: &[i64], runs: u32) {
+    time_runs(program, inputs, runs / 10 + 1, true);
+    let uncached = time_runs(program, inputs, runs, false);
+    let cached = time_runs(program, inputs, runs, true);
+    println!("{} ({} runs)", name, runs);
+    println!("  uncached: {:>10.3?} ({:.3?}/run)", uncached, uncached / runs);
+    println!("  cached:   {:>10.3?} ({:.3?}/run)", cached, cached / runs);
+    println!("  speedup:  {:.2}x", uncached.as_secs_f64() / cached.as_secs_f64());
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let path = args.first().map_or(DAY_05, |p| p.as_str());
+    let runs = args.get(1).map_or(2000, |n| n.parse().expect("runs must be a number"));
+    let source = fs::read_to_string(path).unwrap_or_else(|err| panic!("{}: {}", path, err));
+    let program = source.trim()
+        .split(',')
+        .map(|n| n.parse().unwrap_or_else(|_| panic!("could not parse >{}<", n)))
+        .collect::<Vec<i64>>();
+
+    compare(path, &program, &[1, 5], runs);
+    let countdown = asm::assemble(include_str!("countdown.ic")).expect("valid source");
+    compare("countdown from 100000", &countdown, &[100_000], runs / 100 + 1);
+}
@@ -0,0 +1,145 @@
+use crate::{Cont, IComp, IcompError, Word};
+
+/// A row of machines wired output-to-input. Each machine is primed with
+/// its phase setting before it receives any signal.
+#[derive(Debug)]
+pub struct Chain<W = i64> {
+    pub machines: Vec<IComp<W>>,
+}
+
+impl<W: Word> Chain<W> {
+    pub fn new(program: &[W], phases: &[W]) -> Self {
+        let machines = phases.iter()
+            .map(|&phase| {
+                let mut comp = IComp::from_words(program.to_vec());
+                comp.push_input(phase);
+                comp
+            })
+            .collect();
+        Self { machines }
+    }
+
+    /// Sends `signal` into the first machine and returns the last value
+    /// the final machine produced. In feedback mode the final machine's
+    /// output is fed back into the first until every machine halts, or
+    /// until a full pass moves no signal at all.
+    pub fn run(&mut self, signal: W, feedback: bool) -> Result<Option<W>, IcompError> {
+        let mut pending = vec![signal];
+        let mut last = None;
+        loop {
+            for machine in self.machines.iter_mut() {
+                pending.drain(..).for_each(|value| machine.push_input(value));
+                machine.execute()?;
+                pending = std::mem::take(&mut machine.output);
+            }
+            if let Some(&value) = pending.last() {
+                last = Some(value);
+            }
+            let halted = self.machines.iter().all(|m| m.exec_state == Cont::Halt);
+            if !feedback || halted || pending.is_empty() {
+                return Ok(last);
+            }
+        }
+    }
+}
+
+pub fn run_series<W: Word>(program: &[W], phases: &[W]) -> Result<Option<W>, IcompError> {
+    Chain::new(program, phases).run(W::default(), false)
+}
+
+pub fn run_feedback<W: Word>(program: &[W], phases: &[W]) -> Result<Option<W>, IcompError> {
+    Chain::new(program, phases).run(W::default(), true)
+}
+
+/// Every ordering of `values`, generated with Heap's algorithm.
+pub fn permutations<T: Clone>(values: &[T]) -> Vec<Vec<T>> {
+    let mut current = values.to_vec();
+    let mut counters = vec![0; current.len()];
+    let mut result = vec![current.clone()];
+    let mut i = 0;
+    while i < current.len() {
+        if counters[i] < i {
+            let swap_with = if i % 2 == 0 { 0 } else { counters[i] };
+            current.swap(swap_with, i);
+            result.push(current.clone());
+            counters[i] += 1;
+            i = 0;
+        } else {
+            counters[i] = 0;
+            i += 1;
+        }
+    }
+    result
+}
+
+/// Tries every ordering of `phases` and returns the largest final signal
+/// together with the phase settings that produced it.
+pub fn max_signal<W: Word>(
+    program: &[W],
+    phases: &[W],
+    feedback: bool,
+) -> Result<Option<(W, Vec<W>)>, IcompError> {
+    let mut best: Option<(W, Vec<W>)> = None;
+    for order in permutations(phases) {
+        let signal = Chain::new(program, &order).run(W::default(), feedback)?;
+        if let Some(signal) = signal {
+            if best.as_ref().is_none_or(|(max, _)| signal > *max) {
+                best = Some((signal, order));
+            }
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod amplifiers_tests {
+    use super::*;
+
+    const SERIES: [i64; 17] = [3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0];
+
+    const FEEDBACK: [i64; 29] = [
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26,
+        27, 4, 27, 1001, 28, -1, 28, 1005, 28, 6, 99, 0, 0, 5
+    ];
+
+    #[test]
+    fn permutations_cover_every_ordering_once() {
+        let mut perms = permutations(&[0, 1, 2]);
+        perms.sort();
+        assert_eq!(
+            vec![vec![0, 1, 2], vec![0, 2, 1], vec![1, 0, 2],
+                 vec![1, 2, 0], vec![2, 0, 1], vec![2, 1, 0]],
+            perms
+        );
+        assert_eq!(120, permutations(&[0, 1, 2, 3, 4]).len());
+    }
+
+    #[test]
+    fn series_chain_passes_each_output_to_the_next_machine() {
+        assert_eq!(Ok(Some(43210)), run_series(&SERIES, &[4, 3, 2, 1, 0]));
+    }
+
+    #[test]
+    fn finds_the_phase_settings_with_the_largest_signal() {
+        let best = max_signal(&SERIES, &[0, 1, 2, 3, 4], false);
+        assert_eq!(Ok(Some((43210, vec![4, 3, 2, 1, 0]))), best);
+    }
+
+    #[test]
+    fn feedback_loop_runs_until_every_machine_halts() {
+        assert_eq!(Ok(Some(139629729)), run_feedback(&FEEDBACK, &[9, 8, 7, 6, 5]));
+        let best = max_signal(&FEEDBACK, &[5, 6, 7, 8, 9], true);
+        assert_eq!(Ok(Some((139629729, vec![9, 8, 7, 6, 5]))), best);
+    }
+
+    #[test]
+    fn chains_machines_of_any_word_type() {
+        let program = FEEDBACK.map(i128::from);
+        assert_eq!(Ok(Some(139629729)), run_feedback(&program, &[9, 8, 7, 6, 5]));
+    }
+
+    #[test]
+    fn faults_in_any_machine_are_reported() {
+        assert!(run_series::<i64>(&[3, 0, 42], &[1, 2]).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! Text I/O for programs that talk in ASCII: lines of input go onto the
+//! queue one character code at a time, and output codes up to 127 are
+//! shown as text. Anything larger, usually a final answer, is shown as a
+//! number on a line of its own.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{Cont, IComp, Status, Word};
+
+const NEWLINE: i64 = '\n' as i64;
+
+/// Whether a value is a character code rather than a number.
+pub fn is_ascii<W: Word>(value: W) -> bool {
+    (0..=127).contains(&value.to_i128())
+}
+
+/// Turns output values into text as they arrive, remembering whether the
+/// last one ended a line so that numbers can be put on their own.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Renderer {
+    at_line_start: bool,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self { at_line_start: true }
+    }
+}
+
+impl Renderer {
+    pub fn write<W: Word, O: Write>(&mut self, out: &mut O, value: W) -> io::Result<()> {
+        if is_ascii(value) {
+            let code = value.to_i128() as u8;
+            self.at_line_start = code == b'\n';
+            return out.write_all(&[code]);
+        }
+        if !self.at_line_start {
+            writeln!(out)?;
+        }
+        self.at_line_start = true;
+        writeln!(out, "{}", value)
+    }
+}
+
+/// Renders a whole output buffer at once.
+pub fn render<W: Word>(values: &[W]) -> String {
+    let mut renderer = Renderer::default();
+    let mut out = Vec::new();
+    for &value in values {
+        renderer.write(&mut out, value).expect("writing to a Vec cannot fail");
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+impl<W: Word> IComp<W> {
+    /// Queues a line of text followed by a newline. Characters outside
+    /// ASCII are queued as their code points.
+    pub fn push_line(&mut self, line: &str) {
+        line.chars()
+            .map(|c| c as i64)
+            .chain(std::iter::once(NEWLINE))
+            .for_each(|code| self.push_input(W::from_i64(code)));
+    }
+
+    /// Runs the program as a text console: output is rendered to `out`
+    /// as it is produced, and each time the program wants input the next
+    /// line of `input` is queued. Returns `Cont::NeedsInput` if `input`
+    /// runs dry before the program halts.
+    pub fn run_ascii<R: BufRead, O: Write>(&mut self, input: R, mut out: O) -> io::Result<Cont> {
+        let mut lines = input.lines();
+        let mut renderer = Renderer::default();
+        loop {
+            match self.resume().map_err(io::Error::other)? {
+                Status::Output(value) => renderer.write(&mut out, value)?,
+                Status::NeedsInput => {
+                    out.flush()?;
+                    match lines.next() {
+                        Some(line) => self.push_line(line?.trim_end_matches('\r')),
+                        None => return Ok(Cont::NeedsInput),
+                    }
+                },
+                Status::Halted => {
+                    out.flush()?;
+                    return Ok(Cont::Halt);
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod ascii_tests {
+    use super::*;
+    use crate::asm;
+
+    /// Echoes each line it reads in upper case. An empty line ends the
+    /// session with 1000 plus the number of characters echoed.
+    const SHOUT: &str = "
+        loop:  in [c]
+               eq [c], 10, [t]
+               jnz [t], eol
+               lt [c], 97, [t]
+               jnz [t], echo
+               add [c], -32, [c]
+        echo:  out [c]
+               add [len], 1, [len]
+               add [total], 1, [total]
+               jz 0, loop
+        eol:   jz [len], done
+               out 10
+               mul 0, 0, [len]
+               jz 0, loop
+        done:  add [total], 1000, [total]
+               out [total]
+               hlt
+        c:     data 0
+        t:     data 0
+        len:   data 0
+        total: data 0
+    ";
+
+    fn shout(script: &str) -> (Cont, String) {
+        let mut comp = IComp::from_program(asm::assemble(SHOUT).expect("valid source"));
+        let mut out = Vec::new();
+        let cont = comp.run_ascii(script.as_bytes(), &mut out).unwrap();
+        (cont, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn queues_a_line_as_character_codes() {
+        let mut comp = IComp::from_program(vec![99]);
+        comp.push_line("Hi!");
+        assert_eq!(vec![72, 105, 33, 10], comp.input.into_iter().collect::<Vec<i64>>());
+    }
+
+    #[test]
+    fn renders_large_values_as_numbers_on_their_own_line() {
+        assert_eq!("ok\n", render::<i64>(&[111, 107, 10]));
+        assert_eq!("..\n19690720\n", render::<i64>(&[46, 46, 19690720]));
+        assert_eq!("#\n-1\n128\n", render::<i64>(&[35, 10, -1, 128]));
+    }
+
+    #[test]
+    fn drives_a_program_from_a_script() {
+        assert_eq!((Cont::NeedsInput, "LOOK\nGO NORTH\n".to_string()), shout("look\ngo north\n"));
+        assert_eq!((Cont::Halt, "LOOK\n1004\n".to_string()), shout("look\r\n\n"));
+    }
+}
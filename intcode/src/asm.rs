@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::disasm::ParamKind;
+use crate::isa::InstructionSet;
+use crate::Word;
+
+/// A syntax or resolution error, located by 1-based line and column.
+#[derive(PartialEq, Debug, Clone)]
+pub struct AsmError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// A number or a label, optionally offset by a constant: `loop`, `x+1`.
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(i64),
+    Label { name: String, offset: i64, col: usize },
+}
+
+#[derive(Debug, Clone)]
+struct Operand {
+    mode: i64,
+    expr: Expr,
+    col: usize,
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction { opcode: i64, operands: Vec<Operand> },
+    Data(Vec<Expr>),
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Cursor {
+    fn new(text: &str, line: usize) -> Self {
+        Self { chars: text.chars().collect(), pos: 0, line }
+    }
+
+    fn col(&self) -> usize {
+        self.pos + 1
+    }
+
+    fn error<T>(&self, col: usize, message: String) -> Result<T, AsmError> {
+        Err(AsmError { line: self.line, col, message })
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_ws();
+        self.peek().is_none()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_ws();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), AsmError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            self.unexpected(&format!("'{}'", c))
+        }
+    }
+
+    fn unexpected<T>(&mut self, wanted: &str) -> Result<T, AsmError> {
+        self.skip_ws();
+        let found = match self.peek() {
+            Some(c) => format!("'{}'", c),
+            None => "end of line".to_string(),
+        };
+        self.error(self.col(), format!("expected {}, found {}", wanted, found))
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        self.skip_ws();
+        let start = self.pos;
+        if !self.peek().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') {
+            return None;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        Some(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn number(&mut self) -> Result<i64, AsmError> {
+        self.skip_ws();
+        let start = self.pos;
+        if self.peek() == Some('-') || self.peek() == Some('+') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let digits = self.chars[start..self.pos].iter().collect::<String>();
+        digits.parse::<i64>()
+            .or_else(|_| self.error(start + 1, format!("invalid number '{}'", digits)))
+    }
+
+    fn expr(&mut self) -> Result<Expr, AsmError> {
+        self.skip_ws();
+        let col = self.col();
+        match self.ident() {
+            Some(name) => {
+                let offset = match self.peek_sign() {
+                    true => self.number()?,
+                    false => 0,
+                };
+                Ok(Expr::Label { name, offset, col })
+            },
+            None if self.peek_sign() || self.peek().is_some_and(|c| c.is_ascii_digit()) => {
+                Ok(Expr::Number(self.number()?))
+            },
+            None => self.unexpected("a number or label"),
+        }
+    }
+
+    fn peek_sign(&mut self) -> bool {
+        self.skip_ws();
+        matches!(self.peek(), Some('+') | Some('-'))
+    }
+
+    fn operand(&mut self) -> Result<Operand, AsmError> {
+        self.skip_ws();
+        let col = self.col();
+        if !self.eat('[') {
+            return Ok(Operand { mode: 1, expr: self.expr()?, col });
+        }
+        let save = self.pos;
+        let operand = match self.ident() {
+            Some(base) if base.eq_ignore_ascii_case("rb") && self.peek_sign() => {
+                Operand { mode: 2, expr: Expr::Number(self.number()?), col }
+            },
+            Some(base) if base.eq_ignore_ascii_case("rb") => {
+                Operand { mode: 2, expr: Expr::Number(0), col }
+            },
+            _ => {
+                self.pos = save;
+                Operand { mode: 0, expr: self.expr()?, col }
+            },
+        };
+        self.expect(']')?;
+        Ok(operand)
+    }
+
+    fn separated<T, F>(&mut self, mut item: F) -> Result<Vec<T>, AsmError>
+    where
+        F: FnMut(&mut Self) -> Result<T, AsmError>
+    {
+        let mut items = Vec::new();
+        if self.at_end() {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if self.at_end() {
+                return Ok(items);
+            }
+            self.expect(',')?;
+        }
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find(';') {
+        Some(idx) => &line[..idx],
+        None => line,
+    }
+}
+
+fn parse_statement<W: Word>(cursor: &mut Cursor, isa: &InstructionSet<W>) -> Result<Option<(usize, Statement)>, AsmError> {
+    cursor.skip_ws();
+    let col = cursor.col();
+    let word = match cursor.ident() {
+        Some(word) => word,
+        None if cursor.at_end() => return Ok(None),
+        None => return cursor.unexpected("a mnemonic"),
+    };
+    if word.eq_ignore_ascii_case("data") {
+        let values = cursor.separated(|c| c.expr())?;
+        if values.is_empty() {
+            return cursor.error(col, "data directive needs at least one value".to_string());
+        }
+        return Ok(Some((values.len(), Statement::Data(values))));
+    }
+    let found = isa.by_mnemonic(&word).map(|op| (i64::from(op.code), op.mnemonic, op.params));
+    let (opcode, name, params) = match found {
+        Some(found) => found,
+        None => return cursor.error(col, format!("unknown mnemonic '{}'", word)),
+    };
+    let operands = cursor.separated(|c| c.operand())?;
+    if operands.len() != params.len() {
+        let message = format!("{} expects {} operands, found {}", name, params.len(), operands.len());
+        return cursor.error(col, message);
+    }
+    for (operand, kind) in operands.iter().zip(params.iter()) {
+        if *kind == ParamKind::Write && operand.mode == 1 {
+            let message = format!("{} cannot write to an immediate operand", name);
+            return cursor.error(operand.col, message);
+        }
+    }
+    Ok(Some((operands.len() + 1, Statement::Instruction { opcode, operands })))
+}
+
+fn resolve(expr: &Expr, line: usize, labels: &HashMap<String, usize>) -> Result<i64, AsmError> {
+    match expr {
+        Expr::Number(n) => Ok(*n),
+        Expr::Label { name, offset, col } => match labels.get(name) {
+            Some(&addr) => Ok(addr as i64 + offset),
+            None => Err(AsmError {
+                line,
+                col: *col,
+                message: format!("undefined label '{}'", name),
+            }),
+        },
+    }
+}
+
+/// Assembles mnemonic source into an image `IComp::from_program` can load.
+///
+/// Each line holds an optional `label:`, then an instruction or a `data`
+/// directive, then an optional `;` comment. Operands are immediate when
+/// bare, position mode in brackets (`[x]`), and relative to the relative
+/// base as `[rb+n]`. A label evaluates to its address.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    assemble_with(source, &InstructionSet::<i64>::standard())
+}
+
+/// Assembles source using the mnemonics of a custom instruction set.
+pub fn assemble_with<W: Word>(source: &str, isa: &InstructionSet<W>) -> Result<Vec<i64>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut addr = 0;
+    for (idx, raw) in source.lines().enumerate() {
+        let mut cursor = Cursor::new(strip_comment(raw), idx + 1);
+        let save = cursor.pos;
+        if let Some(name) = cursor.ident() {
+            if cursor.eat(':') {
+                if labels.insert(name.clone(), addr).is_some() {
+                    return cursor.error(save + 1, format!("label '{}' is already defined", name));
+                }
+            } else {
+                cursor.pos = save;
+            }
+        }
+        if let Some((len, statement)) = parse_statement(&mut cursor, isa)? {
+            statements.push((cursor.line, statement));
+            addr += len;
+        }
+    }
+
+    let mut program = Vec::with_capacity(addr);
+    for (line, statement) in statements {
+        match statement {
+            Statement::Instruction { opcode, operands } => {
+                let mut opval = opcode;
+                let mut factor = 100;
+                for operand in operands.iter() {
+                    opval += operand.mode * factor;
+                    factor *= 10;
+                }
+                program.push(opval);
+                for operand in operands.iter() {
+                    program.push(resolve(&operand.expr, line, &labels)?);
+                }
+            },
+            Statement::Data(values) => {
+                for value in values.iter() {
+                    program.push(resolve(value, line, &labels)?);
+                }
+            },
+        }
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod asm_tests {
+    use super::*;
+    use crate::disasm;
+
+    #[test]
+    fn assembles_parameter_modes_and_labels() {
+        let source = "
+            mul [x], 3, [x]   ; becomes 1002
+        x:  data 33
+        ";
+        assert_eq!(Ok(vec![1002, 4, 3, 4, 33]), assemble(source));
+    }
+
+    #[test]
+    fn labels_can_be_used_before_they_are_defined() {
+        let source = "
+            jz 0, end
+            hlt
+        end: out [end+1]
+             hlt
+        ";
+        assert_eq!(Ok(vec![1106, 0, 4, 99, 4, 5, 99]), assemble(source));
+    }
+
+    #[test]
+    fn assembles_relative_operands() {
+        let source = "arb 1\nout [rb-1]\nin [rb]\nHLT";
+        assert_eq!(Ok(vec![109, 1, 204, -1, 203, 0, 99]), assemble(source));
+    }
+
+    #[test]
+    fn round_trips_disassembled_programs() {
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        let source = disasm::disassemble(&program).iter()
+            .map(|line| line.decoded.to_string())
+            .collect::<Vec<String>>()
+            .join("\n");
+        assert_eq!(Ok(program), assemble(&source));
+    }
+
+    #[test]
+    fn reports_unknown_mnemonics_with_line_and_column() {
+        let err = assemble("hlt\n  foo 1, 2").unwrap_err();
+        assert_eq!((2, 3), (err.line, err.col));
+        assert_eq!("2:3: unknown mnemonic 'foo'", err.to_string());
+    }
+
+    #[test]
+    fn reports_undefined_labels() {
+        let err = assemble("out [nowhere]").unwrap_err();
+        assert_eq!((1, 6), (err.line, err.col));
+    }
+
+    #[test]
+    fn reports_wrong_operand_counts_and_immediate_writes() {
+        assert_eq!(1, assemble("add 1, 2").unwrap_err().col);
+        assert_eq!(12, assemble("add 1, 2,  3").unwrap_err().col);
+    }
+
+    #[test]
+    fn reports_malformed_operands() {
+        let err = assemble("out [4").unwrap_err();
+        assert_eq!("1:7: expected ']', found end of line", err.to_string());
+    }
+}
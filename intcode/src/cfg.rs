@@ -0,0 +1,306 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use crate::disasm::{self, Decoded, Line, Mode, Operand};
+use crate::isa::{Control, InstructionSet};
+use crate::{IComp, Instr, Word};
+
+/// How control leaves a block.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum End {
+    /// Runs straight on into the next block.
+    Next,
+    /// Ends in a jump whose target is known.
+    Jump,
+    /// Ends in a jump whose target is read from memory, so it cannot be
+    /// followed without running the program.
+    Unresolved,
+    Halt,
+    /// Runs into words that do not decode as an instruction.
+    Invalid,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum EdgeKind {
+    /// Sequential execution, including a conditional jump not taken.
+    Next,
+    Taken,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// A straight-line run of instructions entered only at its first.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Block<W = i64> {
+    pub start: usize,
+    pub lines: Vec<Line<W>>,
+    pub end: End,
+}
+
+/// The control-flow graph of an image as far as it can be recovered
+/// without running it. Self-modifying code is analysed as loaded.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Cfg<W = i64> {
+    pub blocks: BTreeMap<usize, Block<W>>,
+    pub edges: Vec<Edge>,
+}
+
+/// Where one instruction can go next.
+struct Flow {
+    next: usize,
+    successors: Vec<(usize, EdgeKind)>,
+    end: End,
+}
+
+fn target<W: Word>(operand: &Operand<W>) -> Option<usize> {
+    match operand.mode {
+        Mode::Immediate => usize::try_from(operand.value.to_i128()).ok(),
+        _ => None,
+    }
+}
+
+// A jump whose condition is an immediate constant always or never jumps,
+// which is how Intcode spells an unconditional jump. What each opcode does
+// to control flow comes from the instruction set it was decoded with.
+fn flow<W: Word>(line: &Line<W>, isa: &InstructionSet<W>) -> Flow {
+    let next = line.addr + line.words.len();
+    let operands = match &line.decoded {
+        Decoded::Instruction { operands, .. } => operands,
+        Decoded::Data(_) => return Flow { next, successors: Vec::new(), end: End::Invalid },
+    };
+    let opcode = Instr::decode(line.words[0].to_i128()).opcode;
+    let control = isa.get(opcode).map_or(Control::Next, |op| op.control);
+    match (control, operands.as_slice()) {
+        (Control::Halt, _) => Flow { next, successors: Vec::new(), end: End::Halt },
+        (Control::JumpIfTrue | Control::JumpIfFalse, [condition, destination, ..]) => {
+            let taken = match condition.mode {
+                Mode::Immediate => Some((condition.value != W::default()) == (control == Control::JumpIfTrue)),
+                _ => None,
+            };
+            let mut successors = Vec::new();
+            let mut end = End::Jump;
+            if taken != Some(false) {
+                match target(destination) {
+                    Some(addr) => successors.push((addr, EdgeKind::Taken)),
+                    None => end = End::Unresolved,
+                }
+            }
+            if taken != Some(true) {
+                successors.push((next, EdgeKind::Next));
+            }
+            Flow { next, successors, end }
+        },
+        _ => Flow { next, successors: vec![(next, EdgeKind::Next)], end: End::Next },
+    }
+}
+
+impl<W: Word> Cfg<W> {
+    /// Analyses the image from address 0.
+    pub fn build(program: &[W]) -> Self {
+        Self::from_entries(program, &[0])
+    }
+
+    /// Analyses everything reachable from the given entry points. A new
+    /// block starts at every entry, every jump target and after every
+    /// jump; a jump into the middle of an instruction decodes the words
+    /// from there on as the machine would.
+    pub fn from_entries(program: &[W], entries: &[usize]) -> Self {
+        Self::from_machine(&IComp::from_words(program.to_vec()), entries)
+    }
+
+    /// Like `from_entries`, but on the machine's current memory, decoded
+    /// with its own instruction set.
+    pub fn from_machine(comp: &IComp<W>, entries: &[usize]) -> Self {
+        let mut lines = BTreeMap::new();
+        let mut flows = BTreeMap::new();
+        let mut leaders = entries.iter().copied().collect::<BTreeSet<usize>>();
+        let mut work = entries.to_vec();
+        while let Some(addr) = work.pop() {
+            if lines.contains_key(&addr) {
+                continue;
+            }
+            let line = disasm::line_from(comp, addr);
+            let flow = flow(&line, comp.instruction_set());
+            if flow.end != End::Next {
+                leaders.extend(flow.successors.iter().map(|&(to, _)| to));
+            }
+            work.extend(flow.successors.iter().map(|&(to, _)| to));
+            lines.insert(addr, line);
+            flows.insert(addr, flow);
+        }
+
+        let mut blocks = BTreeMap::new();
+        let mut edges = Vec::new();
+        for &start in leaders.iter() {
+            let mut block = Block { start, lines: Vec::new(), end: End::Next };
+            let mut addr = start;
+            loop {
+                let flow = &flows[&addr];
+                block.lines.push(lines[&addr].clone());
+                block.end = flow.end;
+                if flow.end != End::Next || leaders.contains(&flow.next) {
+                    edges.extend(flow.successors.iter().map(|&(to, kind)| Edge { from: start, to, kind }));
+                    break;
+                }
+                addr = flow.next;
+            }
+            blocks.insert(start, block);
+        }
+        Cfg { blocks, edges }
+    }
+
+    /// Start addresses of the blocks that end in a jump the analysis
+    /// could not follow.
+    pub fn unresolved(&self) -> Vec<usize> {
+        self.blocks.values()
+            .filter(|block| block.end == End::Unresolved)
+            .map(|block| block.start)
+            .collect()
+    }
+
+    /// The graph in Graphviz DOT format. Each block lists its disassembly;
+    /// blocks ending in an unresolved jump are drawn in red with a dashed
+    /// edge to a `?` node.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph intcode {{");
+        let _ = writeln!(out, "    node [shape=box, fontname=\"monospace\"];");
+        for block in self.blocks.values() {
+            let label = block.lines.iter()
+                .map(|line| format!("{}\\l", escape(&line.to_string())))
+                .collect::<String>();
+            let style = match block.end {
+                End::Unresolved | End::Invalid => ", color=red",
+                End::Halt => ", peripheries=2",
+                End::Next | End::Jump => "",
+            };
+            let _ = writeln!(out, "    b{} [label=\"{}\"{}];", block.start, label, style);
+            if block.end == End::Unresolved {
+                let _ = writeln!(out, "    u{} [label=\"?\", shape=circle, color=red];", block.start);
+                let _ = writeln!(out, "    b{} -> u{} [style=dashed, color=red];", block.start, block.start);
+            }
+        }
+        for edge in self.edges.iter() {
+            let style = match edge.kind {
+                EdgeKind::Next => "",
+                EdgeKind::Taken => " [label=\"taken\"]",
+            };
+            let _ = writeln!(out, "    b{} -> b{}{};", edge.from, edge.to, style);
+        }
+        let _ = writeln!(out, "}}");
+        out
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod cfg_tests {
+    use super::*;
+    use crate::asm;
+    use crate::disasm::ParamKind;
+    use crate::fixtures::with_sub;
+    use crate::isa::{Effect, Opcode};
+
+    fn cfg(source: &str) -> Cfg {
+        Cfg::build(&asm::assemble(source).expect("valid source"))
+    }
+
+    fn edges(cfg: &Cfg) -> Vec<(usize, usize, EdgeKind)> {
+        cfg.edges.iter().map(|e| (e.from, e.to, e.kind)).collect()
+    }
+
+    #[test]
+    fn decodes_with_the_machines_instruction_set() {
+        let mut comp = IComp::from_program(vec![1110, 50, 8, 5, 99, 0]);
+        comp.set_instruction_set(with_sub());
+        let cfg = Cfg::from_machine(&comp, &[0]);
+        assert_eq!(End::Halt, cfg.blocks[&0].end);
+        assert!(cfg.blocks[&0].lines[0].to_string().ends_with("SUB 50, 8, [5]"));
+        assert_eq!(End::Invalid, Cfg::build(&comp.program).blocks[&0].end);
+    }
+
+    #[test]
+    fn takes_jumps_from_the_instruction_set_not_the_opcode() {
+        let mut isa = InstructionSet::standard();
+        isa.insert(Opcode::new(5, "OUT5", &[ParamKind::Read], |_, ops| Ok(Effect::Output(ops.value(0)))));
+        let mut comp = IComp::from_program(vec![105, 7, 99]);
+        comp.set_instruction_set(isa);
+        let cfg = Cfg::from_machine(&comp, &[0]);
+        assert_eq!(vec![&0], cfg.blocks.keys().collect::<Vec<&usize>>());
+        assert_eq!(End::Halt, cfg.blocks[&0].end);
+        comp.execute().unwrap();
+        assert_eq!(vec![7], comp.output);
+    }
+
+    #[test]
+    fn splits_a_loop_into_blocks() {
+        let cfg = cfg("
+                  in [n]
+            loop: add [n], -1, [n]
+                  jnz [n], loop
+                  hlt
+            n:    data 0
+        ");
+        assert_eq!(vec![&0, &2, &9], cfg.blocks.keys().collect::<Vec<&usize>>());
+        assert_eq!(End::Jump, cfg.blocks[&2].end);
+        assert_eq!(End::Halt, cfg.blocks[&9].end);
+        assert_eq!(
+            vec![(0, 2, EdgeKind::Next), (2, 2, EdgeKind::Taken), (2, 9, EdgeKind::Next)],
+            edges(&cfg)
+        );
+    }
+
+    #[test]
+    fn constant_conditions_jump_always_or_never() {
+        let cfg = cfg("
+                  jnz 1, end
+                  out 1
+            end:  jz 1, 0
+                  hlt
+        ");
+        assert_eq!(vec![(0, 5, EdgeKind::Taken), (5, 8, EdgeKind::Next)], edges(&cfg));
+        assert!(!cfg.blocks.contains_key(&3));
+    }
+
+    #[test]
+    fn marks_jumps_through_memory_as_unresolved() {
+        let cfg = cfg("
+                  jz [flag], [dest]
+                  hlt
+            flag: data 0
+            dest: data 3
+        ");
+        assert_eq!(End::Unresolved, cfg.blocks[&0].end);
+        assert_eq!(vec![0], cfg.unresolved());
+        assert_eq!(vec![(0, 3, EdgeKind::Next)], edges(&cfg));
+    }
+
+    #[test]
+    fn running_into_data_ends_a_block() {
+        let cfg = cfg("out 5\ndata 42");
+        assert_eq!(End::Invalid, cfg.blocks[&0].end);
+        assert_eq!(2, cfg.blocks[&0].lines.len());
+    }
+
+    #[test]
+    fn exports_dot() {
+        let dot = cfg("
+                  jz [x], [x]
+                  jnz 1, 0
+            x:    data 0
+        ").to_dot();
+        assert!(dot.starts_with("digraph intcode {\n"));
+        assert!(dot.contains("    b0 [label=\"0000: 6,6,6                    JZ [6], [6]\\l\", color=red];\n"), "{}", dot);
+        assert!(dot.contains("    b0 -> u0 [style=dashed, color=red];\n"));
+        assert!(dot.contains("    b3 -> b0 [label=\"taken\"];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,419 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+use crate::disasm;
+use crate::watch::Watchpoint;
+use crate::{Cont, IComp, IcompError, Word};
+
+const HELP: &str = "\
+step [n]          execute n instructions (default 1)
+continue          run until a breakpoint, input request or halt
+break <addr>      set a breakpoint on an instruction address
+delete <addr>     remove a breakpoint
+breakpoints       list breakpoints
+watch <spec>      stop when operands touch memory; spec is addr or start-end,
+                  optionally followed by :r, :w or :rw (default)
+unwatch <spec>    remove a watchpoint
+selfmod           stop on writes to cells that have already run as code
+mem <addr> [n]    show n memory cells starting at addr (default 8)
+poke <addr> <v>   store v at addr
+input <v>...      queue input values
+save <path>       write a snapshot of the machine to a file
+restore <path>    return the machine to a saved snapshot
+info              show iptr, relative base, state and pending I/O
+output            show and clear pending output
+list [addr] [n]   disassemble n instructions from addr (default iptr)
+quit              leave the debugger";
+
+/// Why the debugger stopped executing the program.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Stop {
+    Stepped,
+    Breakpoint(usize),
+    /// The last instruction set off this many watch events.
+    Watch(usize),
+    NeedsInput,
+    Halted,
+    Fault(IcompError),
+}
+
+/// Drives an `IComp` one instruction at a time on behalf of a user.
+#[derive(Debug)]
+pub struct Debugger<W = i64> {
+    pub comp: IComp<W>,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl<W: Word> Debugger<W> {
+    pub fn new(comp: IComp<W>) -> Self {
+        Self { comp, breakpoints: BTreeSet::new() }
+    }
+
+    fn step_once(&mut self) -> Stop {
+        match self.comp.exec_state {
+            Cont::Halt => return Stop::Halted,
+            Cont::NeedsInput if self.comp.input.is_empty() => return Stop::NeedsInput,
+            _ => self.comp.exec_state = Cont::Continue,
+        }
+        let seen = self.comp.watch_events().len();
+        match self.comp.step() {
+            Ok(Some(value)) => self.comp.output.push(value),
+            Ok(None) => {},
+            Err(err) => return Stop::Fault(err),
+        }
+        let events = self.comp.watch_events().len() - seen;
+        if events > 0 {
+            return Stop::Watch(events);
+        }
+        match self.comp.exec_state {
+            Cont::Halt => Stop::Halted,
+            Cont::NeedsInput => Stop::NeedsInput,
+            Cont::Continue => Stop::Stepped,
+        }
+    }
+
+    pub fn step(&mut self, count: usize) -> Stop {
+        let mut stop = Stop::Stepped;
+        for _ in 0..count {
+            stop = self.step_once();
+            if stop != Stop::Stepped {
+                break;
+            }
+        }
+        stop
+    }
+
+    /// Runs until the next breakpoint. A breakpoint on the current
+    /// instruction does not fire, so repeated `continue`s make progress.
+    pub fn cont(&mut self) -> Stop {
+        loop {
+            let stop = self.step_once();
+            if stop != Stop::Stepped {
+                return stop;
+            }
+            if self.breakpoints.contains(&self.comp.iptr) {
+                return Stop::Breakpoint(self.comp.iptr);
+            }
+        }
+    }
+
+    fn describe(&self, stop: Stop) -> String {
+        let here = disasm::line_from(&self.comp, self.comp.iptr);
+        match stop {
+            Stop::Stepped => format!("{}", here),
+            Stop::Breakpoint(addr) => format!("breakpoint at {}\n{}", addr, here),
+            Stop::Watch(count) => {
+                let events = self.comp.watch_events();
+                let events = events[events.len() - count..].iter()
+                    .map(|event| event.to_string())
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                format!("{}\n{}", events, here)
+            },
+            Stop::NeedsInput => format!("waiting for input\n{}", here),
+            Stop::Halted => "program halted".to_string(),
+            Stop::Fault(err) => format!("fault: {}", err),
+        }
+    }
+
+    fn info(&self) -> String {
+        let join = |values: &mut dyn Iterator<Item = &W>| {
+            values.map(|n| n.to_string()).collect::<Vec<String>>().join(", ")
+        };
+        format!(
+            "iptr: {}\nrelative base: {}\nstate: {:?}\npending input: [{}]\npending output: [{}]",
+            self.comp.iptr,
+            self.comp.relative_base,
+            self.comp.exec_state,
+            join(&mut self.comp.input.iter()),
+            join(&mut self.comp.output.iter()),
+        )
+    }
+
+    // Addresses are typed by the user, so they may lie anywhere.
+    fn check_addr(&self, addr: usize) -> Result<(), String> {
+        match addr < self.comp.program.len() {
+            true => Ok(()),
+            false => Err(format!("address {} is outside memory (0..{})", addr, self.comp.program.len())),
+        }
+    }
+
+    fn memory(&self, addr: usize, count: usize) -> Result<String, String> {
+        self.check_addr(addr)?;
+        Ok((addr..addr.saturating_add(count).min(self.comp.program.len()))
+            .map(|a| format!("{:04}: {}", a, self.comp.read(a)))
+            .collect::<Vec<String>>()
+            .join("\n"))
+    }
+
+    fn list(&self, addr: usize, count: usize) -> Result<String, String> {
+        self.check_addr(addr)?;
+        let mut lines = Vec::new();
+        let mut addr = addr;
+        for _ in 0..count {
+            if addr >= self.comp.program.len() {
+                break;
+            }
+            let line = disasm::line_from(&self.comp, addr);
+            let marker = if addr == self.comp.iptr { "=>" } else { "  " };
+            addr = addr.saturating_add(line.words.len());
+            lines.push(format!("{} {}", marker, line));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn snapshot_command(&mut self, cmd: &str, path: Option<&str>) -> Result<String, String> {
+        let path = path.ok_or("missing path")?;
+        if cmd == "save" {
+            self.comp.save_snapshot(path).map_err(|err| err.to_string())?;
+            Ok(format!("saved snapshot to {}", path))
+        } else {
+            self.comp.restore_snapshot(path).map_err(|err| err.to_string())?;
+            Ok(format!("restored snapshot from {}\n{}", path, self.describe(Stop::Stepped)))
+        }
+    }
+
+    fn watch_command(&mut self, cmd: &str, spec: Option<&str>) -> Result<String, String> {
+        let watchpoint = spec.ok_or("missing watch spec")?.parse::<Watchpoint>()?;
+        if cmd == "watch" {
+            self.comp.add_watchpoint(watchpoint);
+            Ok(format!("watching {}", watchpoint))
+        } else if self.comp.remove_watchpoint(&watchpoint) {
+            Ok(format!("watchpoint {} removed", watchpoint))
+        } else {
+            Err(format!("no watchpoint {}", watchpoint))
+        }
+    }
+
+    /// Executes one command line, returning the text to show the user,
+    /// or `None` when the user asked to quit.
+    pub fn command(&mut self, line: &str) -> Option<Result<String, String>> {
+        let mut words = line.split_whitespace();
+        let cmd = words.next().unwrap_or("");
+        if cmd == "save" || cmd == "restore" {
+            return Some(self.snapshot_command(cmd, words.next()));
+        }
+        if cmd == "watch" || cmd == "unwatch" {
+            return Some(self.watch_command(cmd, words.next()));
+        }
+        let args = words.map(|w| w.parse::<W>()
+                .map_err(|_| format!("expected a number, found '{}'", w)))
+            .collect::<Result<Vec<W>, String>>();
+        let args = match args {
+            Ok(args) => args,
+            Err(msg) => return Some(Err(msg)),
+        };
+        let addr = |i: usize| -> Result<usize, String> {
+            let value = *args.get(i).ok_or("missing address")?;
+            usize::try_from(value.to_i128()).map_err(|_| format!("invalid address {}", value))
+        };
+        let count = |i: usize, default: usize| -> Result<usize, String> {
+            args.get(i).map_or(Ok(default), |&n| {
+                usize::try_from(n.to_i128()).map_err(|_| format!("invalid count {}", n))
+            })
+        };
+        let reply = match cmd {
+            "" => Ok(String::new()),
+            "s" | "step" => count(0, 1).map(|n| {
+                let stop = self.step(n);
+                self.describe(stop)
+            }),
+            "c" | "continue" => {
+                let stop = self.cont();
+                Ok(self.describe(stop))
+            },
+            "b" | "break" => addr(0).map(|a| {
+                self.breakpoints.insert(a);
+                format!("breakpoint set at {}", a)
+            }),
+            "d" | "delete" => addr(0).and_then(|a| match self.breakpoints.remove(&a) {
+                true => Ok(format!("breakpoint at {} removed", a)),
+                false => Err(format!("no breakpoint at {}", a)),
+            }),
+            "breakpoints" => Ok(self.breakpoints.iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<String>>()
+                .join("\n")),
+            "x" | "mem" => addr(0).and_then(|a| self.memory(a, count(1, 8)?)),
+            "poke" => addr(0).and_then(|a| {
+                let value = *args.get(1).ok_or("missing value")?;
+                self.comp.write(a, value).map_err(|fault| fault.to_string())?;
+                Ok(format!("{:04}: {}", a, value))
+            }),
+            "selfmod" => {
+                self.comp.detect_self_modification();
+                Ok("detecting self-modifying writes".to_string())
+            },
+            "in" | "input" => {
+                args.iter().for_each(|&v| self.comp.push_input(v));
+                Ok(format!("queued {} value(s)", args.len()))
+            },
+            "i" | "info" => Ok(self.info()),
+            "o" | "output" => Ok(self.comp.output.drain(..)
+                .map(|n| n.to_string())
+                .collect::<Vec<String>>()
+                .join("\n")),
+            "l" | "list" => {
+                let start = if args.is_empty() { Ok(self.comp.iptr) } else { addr(0) };
+                start.and_then(|a| self.list(a, count(1, 10)?))
+            },
+            "h" | "help" => Ok(HELP.to_string()),
+            "q" | "quit" => return None,
+            other => Err(format!("unknown command '{}'; try 'help'", other)),
+        };
+        Some(reply)
+    }
+
+    /// Reads commands from `input` until it is exhausted or the user
+    /// quits, writing replies to `out`.
+    pub fn repl<R: BufRead, O: Write>(&mut self, input: R, mut out: O) -> io::Result<()> {
+        write!(out, "(icd) ")?;
+        out.flush()?;
+        for line in input.lines() {
+            match self.command(&line?) {
+                None => break,
+                Some(Ok(text)) if text.is_empty() => {},
+                Some(Ok(text)) => writeln!(out, "{}", text)?,
+                Some(Err(msg)) => writeln!(out, "error: {}", msg)?,
+            }
+            write!(out, "(icd) ")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod debugger_tests {
+    use super::*;
+    use crate::asm;
+    use crate::fixtures::{with_sub, PATCH_AND_LOOP};
+    use aoc_core::scratch::Scratch;
+
+    fn debugger(source: &str) -> Debugger {
+        let program = asm::assemble(source).expect("valid source");
+        Debugger::new(IComp::from_program(program))
+    }
+
+    fn reply<W: Word>(dbg: &mut Debugger<W>, line: &str) -> String {
+        dbg.command(line).expect("not a quit").expect("command succeeds")
+    }
+
+    const ECHO_TWICE: &str = "
+        loop: in [x]
+              out [x]
+              jnz 1, loop
+        x:    data 0
+    ";
+
+    #[test]
+    fn steps_one_instruction_at_a_time() {
+        let mut dbg = debugger("out 7\nout 8\nhlt");
+        assert_eq!(Stop::Stepped, dbg.step(1));
+        assert_eq!(2, dbg.comp.iptr);
+        assert_eq!(vec![7], dbg.comp.output);
+        assert_eq!(Stop::Halted, dbg.step(5));
+        assert_eq!(vec![7, 8], dbg.comp.output);
+    }
+
+    #[test]
+    fn continue_stops_at_breakpoints_and_input_requests() {
+        let mut dbg = debugger(ECHO_TWICE);
+        reply(&mut dbg, "break 2");
+        reply(&mut dbg, "input 5 6");
+        assert_eq!(Stop::Breakpoint(2), dbg.cont());
+        assert_eq!(Stop::Breakpoint(2), dbg.cont());
+        assert_eq!(Stop::NeedsInput, dbg.cont());
+        assert_eq!(vec![5, 6], dbg.comp.output);
+    }
+
+    #[test]
+    fn can_inspect_and_patch_memory() {
+        let mut dbg = debugger(ECHO_TWICE);
+        reply(&mut dbg, "poke 7 42");
+        assert_eq!("0006: 0\n0007: 42", reply(&mut dbg, "mem 6 2"));
+    }
+
+    #[test]
+    fn clamps_memory_ranges_to_the_image() {
+        let mut dbg = Debugger::new(IComp::<i128>::from_words(vec![104, 7, 99]));
+        let max = usize::MAX.to_string();
+        assert_eq!("0002: 99", reply(&mut dbg, &format!("mem 2 {}", max)));
+        assert_eq!("=> 0000: 104,7                    OUT 7\n   0002: 99                       HLT",
+                   reply(&mut dbg, &format!("list 0 {}", max)));
+        assert!(dbg.command(&format!("mem {} 2", max)).unwrap().is_err());
+        assert!(dbg.command(&format!("list {}", max)).unwrap().is_err());
+    }
+
+    #[test]
+    fn info_shows_iptr_and_pending_output() {
+        let mut dbg = debugger("out 3\nhlt");
+        reply(&mut dbg, "step");
+        let info = reply(&mut dbg, "info");
+        assert!(info.contains("iptr: 2"));
+        assert!(info.contains("pending output: [3]"));
+        assert_eq!("3", reply(&mut dbg, "output"));
+        assert!(dbg.comp.output.is_empty());
+    }
+
+    #[test]
+    fn reports_bad_commands_and_faults() {
+        let mut dbg = debugger("data 42");
+        assert!(dbg.command("frobnicate").unwrap().is_err());
+        assert!(dbg.command("break x").unwrap().is_err());
+        assert!(reply(&mut dbg, "step").starts_with("fault: unknown opcode"));
+        assert!(dbg.command("quit").is_none());
+    }
+
+    #[test]
+    fn repl_reads_commands_until_quit() {
+        let mut dbg = debugger("out 1\nhlt");
+        let mut out = Vec::new();
+        dbg.repl("step\nquit\nstep\n".as_bytes(), &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("0002: 99"));
+        assert_eq!(2, dbg.comp.iptr);
+    }
+
+    #[test]
+    fn can_save_and_restore_checkpoints() {
+        let scratch = Scratch::new("icd-checkpoint");
+        let path = scratch.join("checkpoint");
+        let path = path.to_str().unwrap();
+        let mut dbg = debugger("out 1\nout 2\nhlt");
+        reply(&mut dbg, &format!("save {}", path));
+        reply(&mut dbg, "step 2");
+        assert!(reply(&mut dbg, &format!("restore {}", path)).contains("0000: 104,1"));
+        assert_eq!(0, dbg.comp.iptr);
+        assert!(dbg.command("restore /nonexistent/snapshot").unwrap().is_err());
+    }
+
+    #[test]
+    fn restoring_keeps_the_instruction_set() {
+        let scratch = Scratch::new("icd-checkpoint-isa");
+        let path = scratch.join("checkpoint");
+        let path = path.to_str().unwrap();
+        let mut comp = IComp::from_program(vec![1110, 50, 8, 7, 4, 7, 99, 0]);
+        comp.set_instruction_set(with_sub());
+        let mut dbg = Debugger::new(comp);
+        reply(&mut dbg, &format!("save {}", path));
+        reply(&mut dbg, "step 2");
+        assert!(reply(&mut dbg, &format!("restore {}", path)).contains("SUB 50, 8, [7]"));
+        assert_eq!(Stop::Halted, dbg.cont());
+        assert_eq!(vec![42], dbg.comp.output);
+    }
+
+    #[test]
+    fn watchpoints_and_self_modification_stop_execution() {
+        let mut dbg = debugger(PATCH_AND_LOOP);
+        assert_eq!("watching 0:w", reply(&mut dbg, "watch 0:w"));
+        reply(&mut dbg, "selfmod");
+        let stop = reply(&mut dbg, "continue");
+        let expected = "0002: write [0] 104 -> 99\n0002: self-modifying write [0] 104 -> 99";
+        assert!(stop.starts_with(expected), "{}", stop);
+        assert_eq!(6, dbg.comp.iptr);
+        assert!(dbg.command("unwatch 0:w").unwrap().is_ok());
+        assert!(dbg.command("unwatch 1").unwrap().is_err());
+        assert_eq!(Stop::Halted, dbg.cont());
+    }
+}
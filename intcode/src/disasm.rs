@@ -0,0 +1,226 @@
+use std::fmt;
+
+use crate::{isa, IComp, Instr, Word};
+
+/// Whether an instruction reads a parameter or writes through it.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ParamKind {
+    Read,
+    Write,
+}
+
+/// Mnemonic and parameter layout of the standard opcodes. A machine's
+/// own table is in `IComp::instruction_set`.
+pub fn instruction_info(opcode: i8) -> Option<(&'static str, &'static [ParamKind])> {
+    use ParamKind::*;
+    let info: (&'static str, &'static [ParamKind]) = match opcode {
+        1 => ("ADD", &[Read, Read, Write]),
+        2 => ("MUL", &[Read, Read, Write]),
+        3 => ("IN", &[Write]),
+        4 => ("OUT", &[Read]),
+        5 => ("JNZ", &[Read, Read]),
+        6 => ("JZ", &[Read, Read]),
+        7 => ("LT", &[Read, Read, Write]),
+        8 => ("EQ", &[Read, Read, Write]),
+        9 => ("ARB", &[Read]),
+        99 => ("HLT", &[]),
+        _ => return None,
+    };
+    Some(info)
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl Mode {
+    fn from_digit(digit: u8) -> Option<Self> {
+        match digit {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+}
+
+/// A decoded parameter: position mode prints as `[n]`, relative mode as
+/// `[rb+n]` and immediate mode as the bare value.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Operand<W = i64> {
+    pub mode: Mode,
+    pub value: W,
+}
+
+impl<W: Word> fmt::Display for Operand<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Immediate => write!(f, "{}", self.value),
+            Mode::Relative if self.value < W::default() => write!(f, "[rb{}]", self.value),
+            Mode::Relative => write!(f, "[rb+{}]", self.value),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Decoded<W = i64> {
+    Instruction {
+        mnemonic: &'static str,
+        operands: Vec<Operand<W>>,
+    },
+    Data(W),
+}
+
+/// One row of a listing, covering the words at `addr..addr + words.len()`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Line<W = i64> {
+    pub addr: usize,
+    pub words: Vec<W>,
+    pub decoded: Decoded<W>,
+}
+
+impl<W: Word> fmt::Display for Decoded<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Decoded::Instruction { mnemonic, operands } if operands.is_empty() => {
+                write!(f, "{}", mnemonic)
+            },
+            Decoded::Instruction { mnemonic, operands } => {
+                let operands = operands.iter()
+                    .map(|op| op.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "{} {}", mnemonic, operands)
+            },
+            Decoded::Data(value) => write!(f, "DATA {}", value),
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for Line<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let words = self.words.iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        write!(f, "{:04}: {:<24} {}", self.addr, words, self.decoded)
+    }
+}
+
+/// Decodes the instruction at `addr`, or `None` if the words there are
+/// not a well-formed instruction that fits inside the image.
+fn decode_at<W: Word>(comp: &IComp<W>, addr: usize) -> Option<(usize, Decoded<W>)> {
+    let Instr { opcode, modes } = comp.instr_at(addr);
+    let &isa::Opcode { mnemonic, params, .. } = comp.instruction_set().get(opcode)?;
+    if addr.saturating_add(params.len()) >= comp.program.len() {
+        return None;
+    }
+    let mut operands = Vec::new();
+    for (i, kind) in params.iter().enumerate() {
+        let mode = Mode::from_digit(modes[i])?;
+        if *kind == ParamKind::Write && mode == Mode::Immediate {
+            return None;
+        }
+        operands.push(Operand { mode, value: comp.program[addr + i + 1] });
+    }
+    Some((params.len() + 1, Decoded::Instruction { mnemonic, operands }))
+}
+
+pub(crate) fn line_from<W: Word>(comp: &IComp<W>, addr: usize) -> Line<W> {
+    let (len, decoded) = decode_at(comp, addr)
+        .unwrap_or_else(|| (1, Decoded::Data(comp.read(addr))));
+    Line {
+        addr,
+        words: (addr..addr.saturating_add(len)).map(|a| comp.read(a)).collect(),
+        decoded,
+    }
+}
+
+/// Decodes the single instruction (or data word) starting at `addr`.
+pub fn line_at<W: Word>(program: &[W], addr: usize) -> Line<W> {
+    line_from(&IComp::from_words(program.to_vec()), addr)
+}
+
+/// Walks the image from address 0, decoding each instruction in turn.
+/// Words that do not decode are emitted one at a time as data.
+pub fn disassemble<W: Word>(program: &[W]) -> Vec<Line<W>> {
+    let comp = IComp::from_words(program.to_vec());
+    let mut lines = Vec::new();
+    let mut addr = 0;
+    while addr < program.len() {
+        let line = line_from(&comp, addr);
+        addr += line.words.len();
+        lines.push(line);
+    }
+    lines
+}
+
+pub fn listing<W: Word>(program: &[W]) -> String {
+    disassemble(program).iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[cfg(test)]
+mod disasm_tests {
+    use super::*;
+
+    fn text(program: &[i64]) -> Vec<String> {
+        disassemble(program).iter()
+            .map(|line| line.decoded.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_parameter_modes() {
+        assert_eq!(
+            vec!["MUL [4], 3, [4]", "DATA 33"],
+            text(&[1002, 4, 3, 4, 33])
+        );
+    }
+
+    #[test]
+    fn decodes_relative_mode_and_io() {
+        assert_eq!(
+            vec!["ARB 1", "OUT [rb-1]", "IN [rb+3]", "HLT"],
+            text(&[109, 1, 204, -1, 203, 3, 99])
+        );
+    }
+
+    #[test]
+    fn marks_undecodable_words_as_data() {
+        assert_eq!(
+            vec!["HLT", "DATA 42", "DATA 1101", "DATA 1"],
+            text(&[99, 42, 1101, 1])
+        );
+    }
+
+    #[test]
+    fn marks_immediate_mode_writes_as_data() {
+        let lines = disassemble::<i64>(&[10001, 0, 0, 0]);
+        assert_eq!(Decoded::Data(10001), lines[0].decoded);
+        assert_eq!(4, lines.len());
+    }
+
+    #[test]
+    fn decodes_wide_words() {
+        let value = i64::MAX as i128 * 4;
+        assert_eq!(
+            format!("0000: 104,{} OUT {}\n", value, value),
+            listing(&[104, value])
+        );
+    }
+
+    #[test]
+    fn listing_shows_address_and_raw_words() {
+        assert_eq!(
+            "0000: 1101,100,-1,4            ADD 100, -1, [4]\n\
+             0004: 0                        DATA 0\n",
+            listing::<i64>(&[1101, 100, -1, 4, 0])
+        );
+    }
+}
@@ -0,0 +1,29 @@
+//! Programs and instruction sets used by the tests of more than one
+//! module.
+
+use crate::disasm::ParamKind;
+use crate::isa::{Effect, InstructionSet, Opcode, Operands};
+use crate::{Fault, IComp};
+
+/// Counts `n` down to zero, printing nothing.
+pub const COUNTDOWN: &str = include_str!("../examples/countdown.ic");
+
+/// Prints 7 once, then overwrites its first instruction with a halt
+/// and jumps back to it.
+pub const PATCH_AND_LOOP: &str = "
+    start: out 7
+           add 0, 99, [start]
+           jz 0, start
+";
+
+fn sub(comp: &mut IComp, ops: &Operands) -> Result<Effect, Fault> {
+    comp.write(ops.addr(2), ops.value(0) - ops.value(1))?;
+    Ok(Effect::Next)
+}
+
+/// The standard set plus `SUB a, b, [dest]` as opcode 10.
+pub fn with_sub() -> InstructionSet {
+    let mut isa = InstructionSet::standard();
+    isa.insert(Opcode::new(10, "SUB", &[ParamKind::Read, ParamKind::Read, ParamKind::Write], sub));
+    isa
+}
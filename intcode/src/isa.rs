@@ -0,0 +1,279 @@
+//! The instruction set `IComp` executes, as a table from opcode to
+//! mnemonic, parameter layout and handler. The standard set is loaded by
+//! default; entries can be added, replaced or removed to try out
+//! extended instruction sets without touching the machine itself.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::disasm::{instruction_info, ParamKind};
+use crate::{to_address, Fault, IComp, Word};
+
+/// Opcodes are the last two digits of an instruction's first word.
+const TABLE_SIZE: usize = 100;
+
+/// Parameter modes only reach three parameters, so neither can an
+/// instruction.
+pub const MAX_PARAMS: usize = 3;
+
+const STANDARD: [i8; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+/// An instruction's parameters, resolved through their modes before its
+/// handler runs: `value` for parameters it reads, `addr` for those it
+/// writes through.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Operands<W = i64> {
+    values: [W; MAX_PARAMS],
+    addrs: [usize; MAX_PARAMS],
+}
+
+impl<W: Word> Operands<W> {
+    pub fn value(&self, i: usize) -> W {
+        self.values[i]
+    }
+
+    pub fn addr(&self, i: usize) -> usize {
+        self.addrs[i]
+    }
+}
+
+/// What the machine should do once a handler has run.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Effect<W = i64> {
+    /// Move on to the instruction after this one.
+    Next,
+    Jump(usize),
+    /// Move on, handing a value to the output port.
+    Output(W),
+    /// Stay on this instruction until input has been queued.
+    NeedsInput,
+    Halt,
+}
+
+pub type Handler<W> = fn(&mut IComp<W>, &Operands<W>) -> Result<Effect<W>, Fault>;
+
+/// How an instruction moves the instruction pointer, for analyses that
+/// follow control flow without running the handler.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Control {
+    /// Always moves on to the next instruction.
+    Next,
+    /// Jumps to its second operand when its first is non-zero.
+    JumpIfTrue,
+    /// Jumps to its second operand when its first is zero.
+    JumpIfFalse,
+    Halt,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Opcode<W = i64> {
+    pub code: i8,
+    pub mnemonic: &'static str,
+    pub params: &'static [ParamKind],
+    pub handler: Handler<W>,
+    pub control: Control,
+}
+
+impl<W: Word> Opcode<W> {
+    /// Panics if `code` is not a two-digit opcode or there are more than
+    /// `MAX_PARAMS` parameters. The opcode is taken to run straight on;
+    /// see `with_control` for ones that jump or halt.
+    pub fn new(code: i8, mnemonic: &'static str, params: &'static [ParamKind], handler: Handler<W>) -> Self {
+        assert!((0..TABLE_SIZE as i8).contains(&code), "opcode {} is not in 0..100", code);
+        assert!(params.len() <= MAX_PARAMS, "{} has more than {} parameters", mnemonic, MAX_PARAMS);
+        Self { code, mnemonic, params, handler, control: Control::Next }
+    }
+
+    /// Panics if a jump does not have a condition and a target parameter.
+    pub fn with_control(self, control: Control) -> Self {
+        if matches!(control, Control::JumpIfTrue | Control::JumpIfFalse) {
+            assert!(self.params.len() >= 2, "{} needs a condition and a target to jump", self.mnemonic);
+        }
+        Self { control, ..self }
+    }
+}
+
+#[derive(Clone)]
+pub struct InstructionSet<W = i64> {
+    opcodes: Vec<Option<Opcode<W>>>,
+}
+
+impl<W: Word> InstructionSet<W> {
+    pub fn empty() -> Self {
+        Self { opcodes: vec![None; TABLE_SIZE] }
+    }
+
+    /// The opcodes of the 2019 puzzles.
+    pub fn standard() -> Self {
+        let mut set = Self::empty();
+        for code in STANDARD {
+            let (mnemonic, params) = instruction_info(code).expect("standard opcodes are described");
+            set.insert(Opcode::new(code, mnemonic, params, standard_handler(code)).with_control(standard_control(code)));
+        }
+        set
+    }
+
+    pub fn get(&self, code: i8) -> Option<&Opcode<W>> {
+        usize::try_from(code).ok()
+            .and_then(|i| self.opcodes.get(i))
+            .and_then(Option::as_ref)
+    }
+
+    /// Adds an opcode, returning the one it replaces.
+    pub fn insert(&mut self, opcode: Opcode<W>) -> Option<Opcode<W>> {
+        self.opcodes[opcode.code as usize].replace(opcode)
+    }
+
+    pub fn remove(&mut self, code: i8) -> Option<Opcode<W>> {
+        usize::try_from(code).ok()
+            .and_then(|i| self.opcodes.get_mut(i))
+            .and_then(Option::take)
+    }
+
+    /// Finds an opcode by mnemonic, ignoring case.
+    pub fn by_mnemonic(&self, mnemonic: &str) -> Option<&Opcode<W>> {
+        self.iter().find(|op| op.mnemonic.eq_ignore_ascii_case(mnemonic))
+    }
+
+    /// Every opcode in the set, in numeric order.
+    pub fn iter(&self) -> impl Iterator<Item = &Opcode<W>> {
+        self.opcodes.iter().flatten()
+    }
+}
+
+impl<W: Word> Default for InstructionSet<W> {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl<W> fmt::Debug for InstructionSet<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.opcodes.iter().flatten().map(|op| (op.code, op.mnemonic))).finish()
+    }
+}
+
+fn standard_control(code: i8) -> Control {
+    match code {
+        5 => Control::JumpIfTrue,
+        6 => Control::JumpIfFalse,
+        99 => Control::Halt,
+        _ => Control::Next,
+    }
+}
+
+fn standard_handler<W: Word>(code: i8) -> Handler<W> {
+    match code {
+        1 => |comp, ops| store(comp, ops.addr(2), ops.value(0).add(ops.value(1))),
+        2 => |comp, ops| store(comp, ops.addr(2), ops.value(0).mul(ops.value(1))),
+        3 => |comp, ops| match comp.input.pop_front() {
+            Some(value) => store(comp, ops.addr(0), Some(value)),
+            None => Ok(Effect::NeedsInput),
+        },
+        4 => |_, ops| Ok(Effect::Output(ops.value(0))),
+        5 => |_, ops| jump_if(ops, ops.value(0) != W::default()),
+        6 => |_, ops| jump_if(ops, ops.value(0) == W::default()),
+        7 => |comp, ops| store(comp, ops.addr(2), Some(W::from_i64((ops.value(0) < ops.value(1)) as i64))),
+        8 => |comp, ops| store(comp, ops.addr(2), Some(W::from_i64((ops.value(0) == ops.value(1)) as i64))),
+        9 => |comp, ops| {
+            comp.relative_base = comp.relative_base.add(ops.value(0)).ok_or(Fault::Overflow)?;
+            Ok(Effect::Next)
+        },
+        99 => |_, _| Ok(Effect::Halt),
+        _ => unreachable!("opcode {} is not standard", code),
+    }
+}
+
+// `None` is an arithmetic result that overflowed.
+fn store<W: Word>(comp: &mut IComp<W>, addr: usize, value: Option<W>) -> Result<Effect<W>, Fault> {
+    comp.write(addr, value.ok_or(Fault::Overflow)?)?;
+    Ok(Effect::Next)
+}
+
+fn jump_if<W: Word>(ops: &Operands<W>, condition: bool) -> Result<Effect<W>, Fault> {
+    match condition {
+        true => Ok(Effect::Jump(to_address(ops.value(1))?)),
+        false => Ok(Effect::Next),
+    }
+}
+
+impl<W: Word> IComp<W> {
+    pub fn instruction_set(&self) -> &InstructionSet<W> {
+        &self.isa
+    }
+
+    pub fn set_instruction_set(&mut self, isa: InstructionSet<W>) {
+        self.isa = Arc::new(isa);
+    }
+
+    pub(crate) fn resolve_operands(&self, params: &[ParamKind], modes: [u8; 3]) -> Result<Operands<W>, Fault> {
+        let mut operands = Operands::default();
+        for (i, kind) in params.iter().enumerate() {
+            let param = self.read(self.iptr + i + 1);
+            match kind {
+                ParamKind::Read => operands.values[i] = self.param_val(param, modes[i])?,
+                ParamKind::Write => operands.addrs[i] = self.param_addr(param, modes[i])?,
+            }
+        }
+        Ok(operands)
+    }
+}
+
+#[cfg(test)]
+mod isa_tests {
+    use super::*;
+    use crate::fixtures::with_sub;
+    use crate::{asm, disasm};
+
+    #[test]
+    fn runs_added_opcodes() {
+        let program = asm::assemble_with("sub 50, 8, [x]\nout [x]\nhlt\nx: data 0", &with_sub()).unwrap();
+        assert_eq!(vec![1110, 50, 8, 7, 4, 7, 99, 0], program);
+        let mut comp = IComp::from_program(program);
+        comp.set_instruction_set(with_sub());
+        comp.execute().unwrap();
+        assert_eq!(vec![42], comp.output);
+    }
+
+    #[test]
+    fn disassembles_added_opcodes() {
+        let mut comp = IComp::from_program(vec![10, 5, 6, 0, 99, 9, 2]);
+        comp.set_instruction_set(with_sub());
+        assert_eq!("0000: 10,5,6,0                 SUB [5], [6], [0]", disasm::line_from(&comp, 0).to_string());
+    }
+
+    #[test]
+    fn overrides_standard_opcodes() {
+        let mut isa = InstructionSet::standard();
+        let old = isa.insert(Opcode::new(4, "OUT2", &[ParamKind::Read], |_, ops| Ok(Effect::Output(ops.value(0) * 2))));
+        assert_eq!(Some("OUT"), old.map(|op| op.mnemonic));
+        let mut comp = IComp::from_program(vec![104, 21, 99]);
+        comp.set_instruction_set(isa);
+        comp.execute().unwrap();
+        assert_eq!(vec![42], comp.output);
+    }
+
+    #[test]
+    fn removed_opcodes_fault() {
+        let mut isa = InstructionSet::standard();
+        assert!(isa.remove(2).is_some());
+        assert!(isa.remove(2).is_none());
+        let mut comp = IComp::from_program(vec![2, 0, 0, 0, 99]);
+        comp.set_instruction_set(isa);
+        assert_eq!(Fault::UnknownOpcode, comp.execute().unwrap_err().fault);
+    }
+
+    #[test]
+    fn standard_set_matches_the_disassembler() {
+        let isa = InstructionSet::<i64>::standard();
+        let codes = isa.iter().map(|op| op.code).collect::<Vec<i8>>();
+        assert_eq!(STANDARD.to_vec(), codes);
+        assert_eq!(Some(7), isa.by_mnemonic("lt").map(|op| op.code));
+    }
+
+    #[test]
+    #[should_panic(expected = "more than 3 parameters")]
+    fn rejects_opcodes_with_too_many_parameters() {
+        Opcode::<i64>::new(11, "BIG", &[ParamKind::Read; 4], |_, _| Ok(Effect::Next));
+    }
+}
@@ -0,0 +1,393 @@
+//! An Intcode computer and the tools built around it: an assembler and
+//! disassembler, control-flow graph extraction, a step debugger, execution
+//! tracing and profiling, memory watchpoints, snapshots, ASCII console
+//! I/O, and orchestration of several machines as amplifier chains or
+//! packet networks.
+//!
+//! The machine is generic over its word type (see [`Word`]) and defaults
+//! to wrapping `i64` arithmetic; `Checked` words fault on overflow instead.
+//! Its instructions come from a table (see [`isa::InstructionSet`]) that
+//! can be extended or overridden per machine.
+
+pub mod amplifiers;
+pub mod ascii;
+pub mod asm;
+pub mod cfg;
+pub mod debugger;
+pub mod disasm;
+pub mod isa;
+pub mod network;
+pub mod profile;
+pub mod snapshot;
+pub mod trace;
+pub mod watch;
+pub mod word;
+
+mod text;
+
+#[cfg(test)]
+mod fixtures;
+
+pub use word::{Checked, Word};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+
+use isa::{Effect, InstructionSet};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Cont {
+    Halt,
+    Continue,
+    NeedsInput,
+}
+
+/// The reason `IComp::resume` handed control back to the caller.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Status<W = i64> {
+    NeedsInput,
+    Output(W),
+    Halted,
+}
+
+/// What went wrong while executing an instruction.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Fault {
+    UnknownOpcode,
+    UnknownParamMode(usize),
+    ImmediateModeWrite,
+    NegativeAddress(i128),
+    AddressOutOfRange(i128),
+    Overflow,
+}
+
+/// A fault raised by `IComp`, along with the instruction that raised it.
+/// Values are widened to `i128` so the error does not depend on the word type.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct IcompError {
+    pub iptr: usize,
+    pub opval: i128,
+    pub fault: Fault,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::UnknownOpcode => write!(f, "unknown opcode"),
+            Fault::UnknownParamMode(mode) => write!(f, "unknown parameter mode {}", mode),
+            Fault::ImmediateModeWrite => write!(f, "write parameter in immediate mode"),
+            Fault::NegativeAddress(addr) => write!(f, "negative address {}", addr),
+            Fault::AddressOutOfRange(addr) => write!(f, "address {} is out of range", addr),
+            Fault::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+impl fmt::Display for IcompError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at iptr {} (opcode value {})", self.fault, self.iptr, self.opval)
+    }
+}
+
+impl std::error::Error for IcompError {}
+
+/// An instruction's opcode and parameter modes, decoded from its first
+/// word. Small enough to cache for every address without allocating.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Instr {
+    pub opcode: i8,
+    pub modes: [u8; 3],
+}
+
+impl Instr {
+    /// Mode digits past the third are ignored; no instruction has more
+    /// than three parameters.
+    pub fn decode(opval: i128) -> Self {
+        const OPCODE_FACTOR: i128 = 100;
+        const PARAM_MODE_FACTOR: i128 = 10;
+        let mut modes = [0; 3];
+        let mut params = opval / OPCODE_FACTOR;
+        for mode in modes.iter_mut() {
+            if params <= 0 {
+                break;
+            }
+            *mode = (params % PARAM_MODE_FACTOR) as u8;
+            params /= PARAM_MODE_FACTOR;
+        }
+        Self { opcode: (opval % OPCODE_FACTOR) as i8, modes }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IComp<W = i64> {
+    /// Memory. Once the machine has run, change it through `write` so
+    /// that cached decodes of overwritten instructions are dropped.
+    pub program: Vec<W>,
+    pub iptr: usize,
+    pub relative_base: W,
+    pub input: VecDeque<W>,
+    pub output: Vec<W>,
+    pub exec_state: Cont,
+    trace: Option<trace::Trace<W>>,
+    /// Set while a traced instruction runs, so that writes made between
+    /// steps (by a host or debugger) stay out of the trace.
+    in_traced_step: bool,
+    profile: Option<profile::Profile<W>>,
+    watch: Option<watch::Watch<W>>,
+    decoded: Vec<Option<Instr>>,
+    cache_decodes: bool,
+    isa: Arc<InstructionSet<W>>,
+}
+
+impl IComp {
+    pub fn from_program(program: Vec<i64>) -> Self {
+        Self::from_words(program)
+    }
+}
+
+impl<W: Word> IComp<W> {
+    // Upper bound on how far memory may grow, so a stray write to a huge
+    // address faults instead of exhausting the host.
+    const MAX_MEMORY: usize = 1 << 24;
+
+    /// Loads a program for a machine with any word type. `from_program`
+    /// is the shorthand for the default `i64` machine.
+    pub fn from_words(program: Vec<W>) -> Self {
+        Self {
+            program,
+            iptr: 0,
+            relative_base: W::default(),
+            input: VecDeque::new(),
+            output: Vec::new(),
+            exec_state: Cont::Continue,
+            trace: None,
+            in_traced_step: false,
+            profile: None,
+            watch: None,
+            decoded: Vec::new(),
+            cache_decodes: true,
+            isa: W::standard_isa(),
+        }
+    }
+
+    /// Decoding is cached per address by default. Turning the cache off
+    /// re-decodes every instruction as it executes, which is only useful
+    /// for measuring what the cache saves.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.cache_decodes = enabled;
+        self.decoded.clear();
+    }
+
+    pub fn push_input(&mut self, value: W) {
+        self.input.push_back(value);
+    }
+
+    // Memory past the end of the loaded image reads as zero.
+    pub fn read(&self, addr: usize) -> W {
+        self.program.get(addr).copied().unwrap_or_default()
+    }
+
+    pub fn write(&mut self, addr: usize, value: W) -> Result<(), Fault> {
+        if addr >= Self::MAX_MEMORY {
+            return Err(Fault::AddressOutOfRange(addr as i128));
+        }
+        if addr >= self.program.len() {
+            self.program.resize(addr + 1, W::default());
+        }
+        self.program[addr] = value;
+        if let Some(cached) = self.decoded.get_mut(addr) {
+            *cached = None;
+        }
+        if let Some(trace) = self.trace.as_mut().filter(|_| self.in_traced_step) {
+            trace.record_write(addr, value);
+        }
+        Ok(())
+    }
+
+    /// Decodes the instruction at `addr` without touching the cache.
+    pub fn instr_at(&self, addr: usize) -> Instr {
+        Instr::decode(self.read(addr).to_i128())
+    }
+
+    // Only instructions inside the image are cached; past the end memory
+    // is all zeros, which never decodes to a valid instruction anyway.
+    fn fetch(&mut self) -> Instr {
+        if let Some(&Some(instr)) = self.decoded.get(self.iptr) {
+            return instr;
+        }
+        let instr = self.instr_at(self.iptr);
+        if self.cache_decodes && self.iptr < self.program.len() {
+            if self.decoded.len() < self.program.len() {
+                self.decoded.resize(self.program.len(), None);
+            }
+            self.decoded[self.iptr] = Some(instr);
+        }
+        instr
+    }
+
+    pub(crate) fn param_val(&self, param: W, param_mode: u8) -> Result<W, Fault> {
+        match param_mode {
+            1 => Ok(param),
+            _ => Ok(self.read(self.param_addr(param, param_mode)?)),
+        }
+    }
+
+    // Resolves the address a parameter refers to. Used directly for the
+    // destination of writes, where immediate mode has no meaning.
+    pub(crate) fn param_addr(&self, param: W, param_mode: u8) -> Result<usize, Fault> {
+        let addr = match param_mode {
+            0 => param,
+            1 => return Err(Fault::ImmediateModeWrite),
+            2 => self.relative_base.add(param).ok_or(Fault::Overflow)?,
+            n => return Err(Fault::UnknownParamMode(n as usize)),
+        };
+        to_address(addr)
+    }
+
+    /// The cells the instruction at `iptr` will read and write through
+    /// its operands, worked out before it runs.
+    pub(crate) fn operand_accesses(&self) -> (Vec<usize>, Vec<usize>) {
+        let Instr { opcode, modes } = self.instr_at(self.iptr);
+        let params = self.isa.get(opcode).map_or(&[][..], |op| op.params);
+        let mut reads = Vec::new();
+        let mut writes = Vec::new();
+        for (i, kind) in params.iter().enumerate() {
+            if modes[i] == 1 {
+                continue;
+            }
+            if let Ok(addr) = self.param_addr(self.read(self.iptr + i + 1), modes[i]) {
+                match kind {
+                    disasm::ParamKind::Read => reads.push(addr),
+                    disasm::ParamKind::Write => writes.push(addr),
+                }
+            }
+        }
+        (reads, writes)
+    }
+
+    /// Executes a single instruction, returning the value it wrote to
+    /// the output port, if any. A faulting instruction leaves `iptr`
+    /// pointing at itself.
+    pub fn step(&mut self) -> Result<Option<W>, IcompError> {
+        match self.profile {
+            Some(_) => self.profiled_step(),
+            None => self.unprofiled_step(),
+        }
+    }
+
+    fn unprofiled_step(&mut self) -> Result<Option<W>, IcompError> {
+        match self.trace {
+            Some(_) => self.traced_step(),
+            None => self.untraced_step(),
+        }
+    }
+
+    pub(crate) fn untraced_step(&mut self) -> Result<Option<W>, IcompError> {
+        match self.watch {
+            Some(_) => self.watched_step(),
+            None => self.execute_instruction(),
+        }
+    }
+
+    pub(crate) fn execute_instruction(&mut self) -> Result<Option<W>, IcompError> {
+        let Instr { opcode, modes } = self.fetch();
+        let (params, handler) = match self.isa.get(opcode) {
+            Some(op) => (op.params, op.handler),
+            None => return Err(self.error(Fault::UnknownOpcode)),
+        };
+        let effect = self.resolve_operands(params, modes)
+            .and_then(|operands| handler(self, &operands))
+            .map_err(|fault| self.error(fault))?;
+        let len = 1 + params.len();
+        match effect {
+            Effect::Next => self.iptr += len,
+            Effect::Jump(addr) => self.iptr = addr,
+            Effect::Output(value) => {
+                self.iptr += len;
+                return Ok(Some(value));
+            },
+            Effect::NeedsInput => self.exec_state = Cont::NeedsInput,
+            Effect::Halt => self.exec_state = Cont::Halt,
+        }
+        Ok(None)
+    }
+
+    fn error(&self, fault: Fault) -> IcompError {
+        IcompError {
+            iptr: self.iptr,
+            opval: self.read(self.iptr).to_i128(),
+            fault,
+        }
+    }
+
+    /// Runs until the program produces a value, asks for input that
+    /// has not been queued yet, or halts. Calling it again picks up
+    /// where the previous call left off.
+    pub fn resume(&mut self) -> Result<Status<W>, IcompError> {
+        if self.exec_state == Cont::NeedsInput && !self.input.is_empty() {
+            self.exec_state = Cont::Continue;
+        }
+        while self.exec_state == Cont::Continue {
+            if let Some(value) = self.step()? {
+                return Ok(Status::Output(value));
+            }
+        }
+        match self.exec_state {
+            Cont::NeedsInput => Ok(Status::NeedsInput),
+            _ => Ok(Status::Halted),
+        }
+    }
+
+    /// Runs until the program halts or blocks on input, collecting
+    /// everything it prints into `output`.
+    pub fn execute(&mut self) -> Result<Cont, IcompError> {
+        loop {
+            match self.resume()? {
+                Status::Output(value) => self.output.push(value),
+                Status::NeedsInput => return Ok(Cont::NeedsInput),
+                Status::Halted => return Ok(Cont::Halt),
+            }
+        }
+    }
+}
+
+fn to_address<W: Word>(value: W) -> Result<usize, Fault> {
+    let value = value.to_i128();
+    match usize::try_from(value) {
+        Ok(addr) => Ok(addr),
+        Err(_) if value < 0 => Err(Fault::NegativeAddress(value)),
+        Err(_) => Err(Fault::AddressOutOfRange(value)),
+    }
+}
+
+#[cfg(test)]
+mod icomp_tests {
+    use super::*;
+    use crate::fixtures::PATCH_AND_LOOP;
+
+    #[test]
+    fn decodes_opcode_and_modes() {
+        assert_eq!(Instr { opcode: 2, modes: [0, 1, 0] }, Instr::decode(1002));
+        assert_eq!(Instr { opcode: 1, modes: [2, 2, 2] }, Instr::decode(100022201));
+        assert_eq!(Instr { opcode: -1, modes: [0, 0, 0] }, Instr::decode(-1001));
+    }
+
+    #[test]
+    fn writes_over_executed_code_drop_its_cached_decode() {
+        for cache in [true, false] {
+            let mut comp = IComp::from_program(asm::assemble(PATCH_AND_LOOP).unwrap());
+            comp.set_decode_cache(cache);
+            assert_eq!(Ok(Cont::Halt), comp.execute());
+            assert_eq!(vec![7], comp.output);
+        }
+    }
+
+    #[test]
+    fn writes_to_data_keep_the_cache() {
+        let mut comp = IComp::from_program(asm::assemble("add 1, 2, [x]\nhlt\nx: data 0").unwrap());
+        comp.execute().unwrap();
+        assert_eq!(Some(Instr { opcode: 1, modes: [1, 1, 0] }), comp.decoded[0]);
+        assert_eq!(3, comp.read(5));
+    }
+}
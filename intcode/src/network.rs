@@ -0,0 +1,483 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+use crate::{Cont, IComp, IcompError, Word};
+
+/// How many consecutive quiet passes every machine must report before a
+/// threaded network is considered idle. Threads report independently, so
+/// a single quiet pass can race with a packet that is still in flight.
+const THREADED_QUIET_PASSES: usize = 3;
+
+/// Reports a threaded network may have waiting per machine. A machine
+/// spinning on empty input reports every pass, so once the router falls
+/// behind the machine blocks instead of queueing reports without limit.
+const THREADED_REPORT_BACKLOG: usize = 16;
+
+/// A message between machines, emitted as the output triple `dest, x, y`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Packet<W = i64> {
+    pub dest: W,
+    pub x: W,
+    pub y: W,
+}
+
+/// What the network should do after a monitor has looked at an event.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Action<W = i64> {
+    Continue,
+    Inject(Packet<W>),
+    Stop,
+}
+
+/// Watches traffic on a network. It sees every packet before it is
+/// routed, and is asked what to do whenever the network goes idle;
+/// `Continue` keeps the machines running on empty input.
+pub trait Monitor<W = i64> {
+    fn packet(&mut self, _packet: &Packet<W>) -> Action<W> {
+        Action::Continue
+    }
+
+    fn idle(&mut self) -> Action<W>;
+}
+
+/// Keeps the last packet sent to its address and, whenever the network
+/// goes idle, resends it to machine 0. Stops the network the first time
+/// it would deliver the same `y` value twice in a row.
+#[derive(Debug)]
+pub struct Nat<W = i64> {
+    address: W,
+    last: Option<Packet<W>>,
+    last_delivered_y: Option<W>,
+    pub first_y: Option<W>,
+    pub repeated_y: Option<W>,
+}
+
+impl Nat {
+    pub const DEFAULT_ADDRESS: i64 = 255;
+}
+
+impl<W: Word> Nat<W> {
+    pub fn new(address: W) -> Self {
+        Self {
+            address,
+            last: None,
+            last_delivered_y: None,
+            first_y: None,
+            repeated_y: None,
+        }
+    }
+}
+
+impl<W: Word> Default for Nat<W> {
+    fn default() -> Self {
+        Self::new(W::from_i64(Nat::DEFAULT_ADDRESS))
+    }
+}
+
+impl<W: Word> Monitor<W> for Nat<W> {
+    fn packet(&mut self, packet: &Packet<W>) -> Action<W> {
+        if packet.dest == self.address {
+            self.first_y.get_or_insert(packet.y);
+            self.last = Some(*packet);
+        }
+        Action::Continue
+    }
+
+    fn idle(&mut self) -> Action<W> {
+        let last = match self.last {
+            Some(last) => last,
+            None => return Action::Stop,
+        };
+        if self.last_delivered_y == Some(last.y) {
+            self.repeated_y = Some(last.y);
+            return Action::Stop;
+        }
+        self.last_delivered_y = Some(last.y);
+        Action::Inject(Packet { dest: W::default(), ..last })
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum NetError {
+    Fault { address: usize, error: IcompError },
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Fault { address, error } => write!(f, "machine {}: {}", address, error),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+/// Runs `machine` until it blocks, turning its output into packets.
+/// Partial triples stay in `outbox` until the rest arrives.
+fn pump<W: Word>(
+    address: usize,
+    machine: &mut IComp<W>,
+    outbox: &mut Vec<W>,
+) -> Result<Vec<Packet<W>>, NetError> {
+    machine.execute().map_err(|error| NetError::Fault { address, error })?;
+    outbox.append(&mut machine.output);
+    let complete = outbox.len() - outbox.len() % 3;
+    Ok(outbox.drain(..complete)
+        .collect::<Vec<W>>()
+        .chunks(3)
+        .map(|c| Packet { dest: c[0], x: c[1], y: c[2] })
+        .collect())
+}
+
+/// Machines that each learn their address from their first input, then
+/// exchange packets. A machine that asks for input with nothing queued
+/// receives -1.
+#[derive(Debug)]
+pub struct Network<W = i64> {
+    pub machines: Vec<IComp<W>>,
+    queues: Vec<VecDeque<(W, W)>>,
+    outboxes: Vec<Vec<W>>,
+}
+
+// Packets for addresses outside the network have no local destination.
+fn local_address<W: Word>(dest: W, size: usize) -> Option<usize> {
+    usize::try_from(dest.to_i128()).ok().filter(|&d| d < size)
+}
+
+impl<W: Word> Network<W> {
+    pub fn boot(program: &[W], size: usize) -> Self {
+        let machines = (0..size)
+            .map(|address| {
+                let mut comp = IComp::from_words(program.to_vec());
+                comp.push_input(W::from_i64(address as i64));
+                comp
+            })
+            .collect();
+        Self {
+            machines,
+            queues: vec![VecDeque::new(); size],
+            outboxes: vec![Vec::new(); size],
+        }
+    }
+
+    /// Hands a packet to its destination's queue. Packets for addresses
+    /// outside the network are only seen by the monitor.
+    fn route(&mut self, packet: Packet<W>) {
+        if let Some(dest) = local_address(packet.dest, self.machines.len()) {
+            self.queues[dest].push_back((packet.x, packet.y));
+        }
+    }
+
+    /// Gives each machine a turn in address order until the monitor
+    /// stops the network or every machine has halted.
+    pub fn run(&mut self, monitor: &mut dyn Monitor<W>) -> Result<(), NetError> {
+        loop {
+            if self.machines.iter().all(|m| m.exec_state == Cont::Halt) {
+                return Ok(());
+            }
+            let mut traffic = false;
+            for address in 0..self.machines.len() {
+                let machine = &mut self.machines[address];
+                if machine.exec_state == Cont::Halt {
+                    continue;
+                }
+                if self.queues[address].is_empty() {
+                    machine.push_input(W::from_i64(-1));
+                }
+                for (x, y) in self.queues[address].drain(..) {
+                    machine.push_input(x);
+                    machine.push_input(y);
+                    traffic = true;
+                }
+                for packet in pump(address, machine, &mut self.outboxes[address])? {
+                    traffic = true;
+                    match monitor.packet(&packet) {
+                        Action::Stop => return Ok(()),
+                        Action::Inject(extra) => self.route(extra),
+                        Action::Continue => {},
+                    }
+                    self.route(packet);
+                }
+            }
+            if !traffic && self.queues.iter().all(|q| q.is_empty()) {
+                match monitor.idle() {
+                    Action::Inject(packet) => self.route(packet),
+                    Action::Continue => {},
+                    Action::Stop => return Ok(()),
+                }
+            }
+        }
+    }
+
+    /// Runs every machine on its own thread, with the calling thread
+    /// routing packets and consulting the monitor.
+    pub fn run_threaded(self, monitor: &mut dyn Monitor<W>) -> Result<(), NetError> {
+        enum Report<W> {
+            Packet { from: usize, packet: Packet<W> },
+            Quiet { address: usize, received: usize },
+            Halted(usize),
+            Fault(NetError),
+        }
+
+        let size = self.machines.len();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (report_tx, report_rx) = mpsc::sync_channel(size * THREADED_REPORT_BACKLOG);
+        let mut inboxes = Vec::new();
+        let mut handles = Vec::new();
+        for (address, mut machine) in self.machines.into_iter().enumerate() {
+            let (inbox_tx, inbox_rx) = mpsc::channel::<(W, W)>();
+            inboxes.push(inbox_tx);
+            let report_tx = report_tx.clone();
+            let stop = Arc::clone(&stop);
+            let mut outbox = self.outboxes[address].clone();
+            handles.push(thread::spawn(move || {
+                let mut received = 0;
+                while !stop.load(Ordering::Relaxed) {
+                    let before = received;
+                    while let Ok((x, y)) = inbox_rx.try_recv() {
+                        machine.push_input(x);
+                        machine.push_input(y);
+                        received += 1;
+                    }
+                    if received == before {
+                        machine.push_input(W::from_i64(-1));
+                    }
+                    let packets = match pump(address, &mut machine, &mut outbox) {
+                        Ok(packets) => packets,
+                        Err(err) => {
+                            let _ = report_tx.send(Report::Fault(err));
+                            return;
+                        },
+                    };
+                    let quiet = received == before && packets.is_empty();
+                    for packet in packets {
+                        let _ = report_tx.send(Report::Packet { from: address, packet });
+                    }
+                    if machine.exec_state == Cont::Halt {
+                        let _ = report_tx.send(Report::Halted(address));
+                        return;
+                    }
+                    if quiet {
+                        let _ = report_tx.send(Report::Quiet { address, received });
+                        thread::yield_now();
+                    }
+                }
+            }));
+        }
+        drop(report_tx);
+
+        for (address, queue) in self.queues.into_iter().enumerate() {
+            for packet in queue {
+                let _ = inboxes[address].send(packet);
+            }
+        }
+        let mut delivered = vec![0; size];
+        let mut quiet_passes = vec![0; size];
+        let deliver = |packet: Packet<W>, delivered: &mut Vec<usize>, quiet: &mut Vec<usize>| {
+            if let Some(dest) = local_address(packet.dest, size) {
+                if inboxes[dest].send((packet.x, packet.y)).is_ok() {
+                    delivered[dest] += 1;
+                    quiet[dest] = 0;
+                }
+            }
+        };
+        let mut result = Ok(());
+        for report in report_rx.iter() {
+            match report {
+                Report::Packet { from, packet } => {
+                    quiet_passes[from] = 0;
+                    match monitor.packet(&packet) {
+                        Action::Stop => break,
+                        Action::Inject(extra) => deliver(extra, &mut delivered, &mut quiet_passes),
+                        Action::Continue => {},
+                    }
+                    deliver(packet, &mut delivered, &mut quiet_passes);
+                },
+                // A quiet pass only counts if the machine had already
+                // seen every packet routed to it.
+                Report::Quiet { address, received } if received == delivered[address] => {
+                    quiet_passes[address] += 1;
+                },
+                Report::Quiet { address, .. } => quiet_passes[address] = 0,
+                Report::Halted(address) => quiet_passes[address] = usize::MAX,
+                Report::Fault(err) => {
+                    result = Err(err);
+                    break;
+                },
+            }
+            if quiet_passes.iter().all(|&q| q >= THREADED_QUIET_PASSES) {
+                match monitor.idle() {
+                    Action::Inject(packet) => deliver(packet, &mut delivered, &mut quiet_passes),
+                    // Ask again only after another full set of quiet passes.
+                    Action::Continue => quiet_passes.iter_mut()
+                        .filter(|q| **q != usize::MAX)
+                        .for_each(|q| *q = 0),
+                    Action::Stop => break,
+                }
+            }
+        }
+        stop.store(true, Ordering::Relaxed);
+        // Machines blocked on a full report channel fail their send and
+        // see the stop flag once the receiver is gone.
+        drop(report_rx);
+        for handle in handles {
+            let _ = handle.join();
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod network_tests {
+    use super::*;
+    use crate::asm;
+
+    /// Machine 0 sends `(1, 0, 1)`. Every machine forwards what it
+    /// receives to the next address, with itself as `x`; the last one in
+    /// a ring of four sends to 255.
+    const RELAY: &str = "
+              in [addr]
+              jnz [addr], loop
+              out 1
+              out 0
+              out 1
+        loop: in [x]
+              eq [x], -1, [tmp]
+              jnz [tmp], loop
+              in [y]
+              add [addr], 1, [dest]
+              eq [dest], 4, [tmp]
+              jz [tmp], send
+              add 255, 0, [dest]
+        send: out [dest]
+              out [addr]
+              out [y]
+              jz 0, loop
+        addr: data 0
+        x:    data 0
+        y:    data 0
+        dest: data 0
+        tmp:  data 0
+    ";
+
+    fn relay() -> Vec<i64> {
+        asm::assemble(RELAY).expect("valid source")
+    }
+
+    struct FirstPacket(Option<Packet>);
+
+    impl Monitor for FirstPacket {
+        fn packet(&mut self, packet: &Packet) -> Action {
+            if packet.dest == Nat::DEFAULT_ADDRESS {
+                self.0 = Some(*packet);
+                return Action::Stop;
+            }
+            Action::Continue
+        }
+
+        fn idle(&mut self) -> Action {
+            Action::Stop
+        }
+    }
+
+    #[test]
+    fn routes_packets_between_machines() {
+        let mut network = Network::boot(&relay(), 4);
+        let mut monitor = FirstPacket(None);
+        network.run(&mut monitor).unwrap();
+        assert_eq!(Some(Packet { dest: 255, x: 3, y: 1 }), monitor.0);
+    }
+
+    #[test]
+    fn nat_resends_when_idle_until_a_value_repeats() {
+        let mut network = Network::boot(&relay(), 4);
+        let mut nat = Nat::new(Nat::DEFAULT_ADDRESS);
+        network.run(&mut nat).unwrap();
+        assert_eq!(Some(1), nat.first_y);
+        assert_eq!(Some(1), nat.repeated_y);
+    }
+
+    #[test]
+    fn silent_network_stops_when_idle() {
+        let mut network = Network::boot(&asm::assemble("l: in [9]\njz 0, l").unwrap(), 3);
+        let mut nat = Nat::new(Nat::DEFAULT_ADDRESS);
+        network.run(&mut nat).unwrap();
+        assert_eq!(None, nat.first_y);
+    }
+
+    #[test]
+    fn threaded_run_matches_round_robin() {
+        let network = Network::boot(&relay(), 4);
+        let mut nat = Nat::new(Nat::DEFAULT_ADDRESS);
+        network.run_threaded(&mut nat).unwrap();
+        assert_eq!(Some(1), nat.first_y);
+        assert_eq!(Some(1), nat.repeated_y);
+    }
+
+    /// Sends `(255, addr, 5)` once it has read five inputs, then halts.
+    const PATIENT: &str = "
+              in [addr]
+        loop: in [x]
+              add [n], 1, [n]
+              eq [n], 5, [tmp]
+              jz [tmp], loop
+              out 255
+              out [addr]
+              out [n]
+              hlt
+        addr: data 0
+        x:    data 0
+        n:    data 0
+        tmp:  data 0
+    ";
+
+    /// Lets the network idle along until a packet for 255 turns up.
+    #[derive(Default)]
+    struct Patient {
+        idles: usize,
+        seen: Option<Packet>,
+    }
+
+    impl Monitor for Patient {
+        fn packet(&mut self, packet: &Packet) -> Action {
+            self.seen = Some(*packet);
+            Action::Stop
+        }
+
+        fn idle(&mut self) -> Action {
+            self.idles += 1;
+            Action::Continue
+        }
+    }
+
+    #[test]
+    fn continuing_through_idle_keeps_the_network_running() {
+        let program = asm::assemble(PATIENT).unwrap();
+        let mut monitor = Patient::default();
+        Network::boot(&program, 1).run(&mut monitor).unwrap();
+        assert_eq!(Some(Packet { dest: 255, x: 0, y: 5 }), monitor.seen);
+        assert!(monitor.idles >= 3);
+        let mut monitor = Patient::default();
+        Network::boot(&program, 1).run_threaded(&mut monitor).unwrap();
+        assert_eq!(Some(Packet { dest: 255, x: 0, y: 5 }), monitor.seen);
+    }
+
+    #[test]
+    fn runs_machines_of_any_word_type() {
+        let program = relay().into_iter().map(i128::from).collect::<Vec<i128>>();
+        let mut nat = Nat::<i128>::default();
+        Network::boot(&program, 4).run(&mut nat).unwrap();
+        assert_eq!((Some(1), Some(1)), (nat.first_y, nat.repeated_y));
+    }
+
+    #[test]
+    fn faults_name_the_machine() {
+        let mut network: Network = Network::boot(&[3, 10, 42], 2);
+        let err = network.run(&mut Nat::default()).unwrap_err();
+        assert_eq!("machine 0: unknown opcode at iptr 2 (opcode value 42)", err.to_string());
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::disasm;
+use crate::{Cont, IComp, IcompError, Instr, Word};
+
+/// Execution counts gathered while a program runs. Reads and writes are
+/// the memory cells touched through position- or relative-mode operands.
+/// `code` keeps each instruction as it was last executed, decoded with
+/// the machine's own instruction set, so the report stays accurate for
+/// programs that rewrite themselves or run added opcodes.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Profile<W = i64> {
+    pub cycles: u64,
+    pub code: BTreeMap<usize, disasm::Line<W>>,
+    pub mnemonics: BTreeMap<i8, &'static str>,
+    pub by_addr: BTreeMap<usize, u64>,
+    pub by_opcode: BTreeMap<i8, u64>,
+    pub reads: BTreeMap<usize, u64>,
+    pub writes: BTreeMap<usize, u64>,
+}
+
+/// Entries sorted by descending count, ties broken by key.
+fn ranked<K: Copy + Ord>(counts: &BTreeMap<K, u64>) -> Vec<(K, u64)> {
+    let mut ranked = counts.iter().map(|(&k, &n)| (k, n)).collect::<Vec<(K, u64)>>();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked
+}
+
+impl<W: Word> Profile<W> {
+    fn percent(&self, count: u64) -> f64 {
+        100.0 * count as f64 / self.cycles.max(1) as f64
+    }
+
+    fn mnemonic(&self, opcode: i8) -> String {
+        self.mnemonics.get(&opcode).map_or_else(|| format!("OP{}", opcode), |name| name.to_string())
+    }
+
+    /// A ranked listing of the `top` hottest instructions with their
+    /// disassembly, followed by opcode totals and the busiest cells.
+    pub fn report(&self, top: usize) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "cycles: {}", self.cycles);
+        let _ = writeln!(out, "\nhot spots:");
+        for (rank, (addr, count)) in ranked(&self.by_addr).into_iter().take(top).enumerate() {
+            let _ = writeln!(out, "{:>4}. {:>10} {:>6.2}%  {}",
+                             rank + 1, count, self.percent(count), self.code[&addr]);
+        }
+        let _ = writeln!(out, "\nopcodes:");
+        for (opcode, count) in ranked(&self.by_opcode) {
+            let _ = writeln!(out, "  {:<4} {:>10} {:>6.2}%", self.mnemonic(opcode), count, self.percent(count));
+        }
+        for (title, cells) in [("cells read", &self.reads), ("cells written", &self.writes)] {
+            let _ = writeln!(out, "\n{}:", title);
+            for (addr, count) in ranked(cells).into_iter().take(top) {
+                let _ = writeln!(out, "  {:04} {:>10}", addr, count);
+            }
+        }
+        out
+    }
+
+    /// Counts in the folded-stack format flame graph tools read: one
+    /// `frame;frame count` line per instruction address, grouped under
+    /// its mnemonic.
+    pub fn folded(&self) -> String {
+        self.by_addr.iter()
+            .map(|(&addr, count)| {
+                let opcode = self.code.get(&addr).map_or(0, |line| Instr::decode(line.words[0].to_i128()).opcode);
+                format!("intcode;{};{:04} {}\n", self.mnemonic(opcode), addr, count)
+            })
+            .collect()
+    }
+
+    pub fn save_folded<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.folded())
+    }
+}
+
+impl<W: Word> IComp<W> {
+    /// Starts counting executed instructions and memory traffic.
+    pub fn start_profile(&mut self) {
+        self.profile = Some(Profile::default());
+    }
+
+    pub fn take_profile(&mut self) -> Option<Profile<W>> {
+        self.profile.take()
+    }
+
+    /// Wraps a single instruction with profile bookkeeping. Like tracing,
+    /// instructions that fault or block on input are not counted.
+    pub(crate) fn profiled_step(&mut self) -> Result<Option<W>, IcompError> {
+        let addr = self.iptr;
+        let opcode = self.instr_at(addr).opcode;
+        let mnemonic = self.isa.get(opcode).map(|op| op.mnemonic);
+        let (reads, writes) = self.operand_accesses();
+        let line = disasm::line_from(self, addr);
+        let result = self.unprofiled_step();
+        if result.is_ok() && self.exec_state != Cont::NeedsInput {
+            if let Some(profile) = self.profile.as_mut() {
+                profile.cycles += 1;
+                profile.code.insert(addr, line);
+                profile.mnemonics.extend(mnemonic.map(|name| (opcode, name)));
+                *profile.by_addr.entry(addr).or_insert(0) += 1;
+                *profile.by_opcode.entry(opcode).or_insert(0) += 1;
+                for cell in reads {
+                    *profile.reads.entry(cell).or_insert(0) += 1;
+                }
+                for cell in writes {
+                    *profile.writes.entry(cell).or_insert(0) += 1;
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod profile_tests {
+    use super::*;
+    use crate::asm;
+    use crate::fixtures::{with_sub, COUNTDOWN};
+
+    fn profiled(n: i64) -> Profile {
+        let program = asm::assemble(COUNTDOWN).expect("valid source");
+        let mut comp = IComp::from_program(program);
+        comp.push_input(n);
+        comp.start_profile();
+        comp.execute().unwrap();
+        comp.take_profile().unwrap()
+    }
+
+    #[test]
+    fn counts_cycles_addresses_and_opcodes() {
+        let profile = profiled(5);
+        assert_eq!(12, profile.cycles);
+        assert_eq!(Some(&5), profile.by_addr.get(&2));
+        assert_eq!(Some(&5), profile.by_opcode.get(&5));
+        assert_eq!(Some(&1), profile.by_opcode.get(&99));
+    }
+
+    #[test]
+    fn counts_reads_and_writes_per_cell() {
+        let profile = profiled(3);
+        assert_eq!(Some(&6), profile.reads.get(&10));
+        assert_eq!(Some(&4), profile.writes.get(&10));
+        assert_eq!(1, profile.reads.len());
+    }
+
+    #[test]
+    fn report_ranks_hot_spots_with_disassembly() {
+        let profile = profiled(4);
+        let report = profile.report(2);
+        let hot = report.lines().skip(3).take(2).collect::<Vec<&str>>();
+        assert!(hot[0].contains("0002: 1001,10,-1,10"), "{}", hot[0]);
+        assert!(hot[0].contains("ADD [10], -1, [10]"));
+        assert!(hot[1].contains("JNZ [10], 2"));
+    }
+
+    #[test]
+    fn exports_folded_stacks() {
+        let profile = profiled(2);
+        assert_eq!(
+            "intcode;IN;0000 1\nintcode;ADD;0002 2\nintcode;JNZ;0006 2\nintcode;HLT;0009 1\n",
+            profile.folded()
+        );
+    }
+
+    #[test]
+    fn names_opcodes_from_the_machines_instruction_set() {
+        let mut comp = IComp::from_program(vec![1110, 50, 8, 5, 99, 0]);
+        comp.set_instruction_set(with_sub());
+        comp.start_profile();
+        comp.execute().unwrap();
+        let profile = comp.take_profile().unwrap();
+        assert!(profile.report(1).contains("SUB 50, 8, [5]"), "{}", profile.report(1));
+        assert_eq!("intcode;SUB;0000 1\nintcode;HLT;0004 1\n", profile.folded());
+    }
+
+    #[test]
+    fn profiling_and_tracing_can_run_together() {
+        let mut comp = IComp::from_program(asm::assemble(COUNTDOWN).unwrap());
+        comp.push_input(2);
+        comp.start_profile();
+        comp.start_trace();
+        comp.execute().unwrap();
+        assert_eq!(6, comp.take_profile().unwrap().cycles);
+        assert_eq!(6, comp.take_trace().unwrap().entries.len());
+    }
+
+    #[test]
+    fn reports_self_modified_instructions_as_executed() {
+        let program = asm::assemble("
+                mul [patch], 3, [patch]
+        patch:  data 33
+        ").unwrap();
+        let mut comp = IComp::from_program(program);
+        comp.start_profile();
+        comp.execute().unwrap();
+        comp.program[4] = 0;
+        let report = comp.take_profile().unwrap().report(5);
+        assert!(report.contains("0004: 99"), "{}", report);
+    }
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::text::{field, join, parse_list, parse_one};
+use crate::{Cont, IComp, Word};
+
+const MAGIC: &str = "intcode-snapshot";
+
+/// Bumped whenever the layout below changes. Older versions stay
+/// loadable for as long as we know how to read them.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+fn state_name(state: Cont) -> &'static str {
+    match state {
+        Cont::Halt => "halt",
+        Cont::Continue => "continue",
+        Cont::NeedsInput => "needs-input",
+    }
+}
+
+fn parse_state(s: &str) -> Result<Cont, String> {
+    match s {
+        "halt" => Ok(Cont::Halt),
+        "continue" => Ok(Cont::Continue),
+        "needs-input" => Ok(Cont::NeedsInput),
+        _ => Err(format!("unknown execution state >{}<", s)),
+    }
+}
+
+impl<W: Word> IComp<W> {
+    /// Serialises everything needed to resume this machine later: memory,
+    /// registers, execution state and any queued input or buffered output.
+    /// An active trace is not part of the snapshot.
+    pub fn to_snapshot(&self) -> String {
+        format!(
+            "{} {}\niptr {}\nrelative-base {}\nstate {}\ninput {}\noutput {}\nmemory {}\n",
+            MAGIC,
+            SNAPSHOT_VERSION,
+            self.iptr,
+            self.relative_base,
+            state_name(self.exec_state),
+            join(self.input.iter()),
+            join(self.output.iter()),
+            join(self.program.iter()),
+        )
+    }
+
+    pub fn from_snapshot(s: &str) -> Result<Self, String> {
+        let mut lines = s.lines();
+        let version = field(&mut lines, MAGIC)
+            .map_err(|_| "not an intcode snapshot".to_string())?;
+        let version = parse_one::<u32>(version)?;
+        if version > SNAPSHOT_VERSION {
+            return Err(format!(
+                "snapshot version {} is newer than the supported version {}",
+                version, SNAPSHOT_VERSION
+            ));
+        }
+        let iptr = parse_one(field(&mut lines, "iptr")?)?;
+        let relative_base = parse_one(field(&mut lines, "relative-base")?)?;
+        let exec_state = parse_state(field(&mut lines, "state")?)?;
+        let input = parse_list::<W>(field(&mut lines, "input")?)?;
+        let output = parse_list(field(&mut lines, "output")?)?;
+        let program = parse_list(field(&mut lines, "memory")?)?;
+        let mut comp = IComp::from_words(program);
+        comp.iptr = iptr;
+        comp.relative_base = relative_base;
+        comp.exec_state = exec_state;
+        comp.input = input.into_iter().collect();
+        comp.output = output;
+        Ok(comp)
+    }
+
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_snapshot())
+    }
+
+    pub fn load_snapshot<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_snapshot(&fs::read_to_string(path)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Puts this machine back into a saved state. Only what a snapshot
+    /// holds is replaced; the instruction set, watchpoints and any trace
+    /// or profile carry on as they were.
+    pub fn restore_snapshot<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let saved = Self::load_snapshot(path)?;
+        self.program = saved.program;
+        self.iptr = saved.iptr;
+        self.relative_base = saved.relative_base;
+        self.exec_state = saved.exec_state;
+        self.input = saved.input;
+        self.output = saved.output;
+        self.decoded.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use super::*;
+    use crate::{asm, Status};
+    use aoc_core::scratch::Scratch;
+
+    const ACCUMULATOR: &str = "
+        loop: in [x]
+              add [x], [total], [total]
+              out [total]
+              jnz 1, loop
+        x:     data 0
+        total: data 0
+    ";
+
+    fn accumulator() -> IComp {
+        IComp::from_program(asm::assemble(ACCUMULATOR).expect("valid source"))
+    }
+
+    #[test]
+    fn restored_machine_continues_where_the_original_stopped() {
+        let mut comp = accumulator();
+        comp.push_input(5);
+        comp.push_input(7);
+        assert_eq!(Ok(Status::Output(5)), comp.resume());
+
+        let mut restored = IComp::from_snapshot(&comp.to_snapshot()).unwrap();
+        assert_eq!(comp.program, restored.program);
+        assert_eq!(Ok(Status::Output(12)), restored.resume());
+        assert_eq!(Ok(Status::NeedsInput), restored.resume());
+    }
+
+    #[test]
+    fn preserves_state_registers_and_buffers() {
+        let mut comp = accumulator();
+        comp.push_input(3);
+        comp.execute().unwrap();
+        comp.push_input(9);
+        comp.relative_base = -4;
+        let restored = IComp::from_snapshot(&comp.to_snapshot()).unwrap();
+        assert_eq!(Cont::NeedsInput, restored.exec_state);
+        assert_eq!(comp.iptr, restored.iptr);
+        assert_eq!(-4, restored.relative_base);
+        assert_eq!(vec![9], restored.input.into_iter().collect::<Vec<i64>>());
+        assert_eq!(vec![3], restored.output);
+    }
+
+    #[test]
+    fn forks_from_a_warm_state() {
+        let mut comp = accumulator();
+        comp.push_input(10);
+        comp.execute().unwrap();
+        let checkpoint = comp.to_snapshot();
+        for (branch, expected) in [(1, 11), (-10, 0)] {
+            let mut fork = IComp::<i64>::from_snapshot(&checkpoint).unwrap();
+            fork.push_input(branch);
+            assert_eq!(Ok(Status::Output(expected)), fork.resume());
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip_through_a_file() {
+        let scratch = Scratch::new("icomp-snapshot");
+        let path = scratch.join("machine");
+        let comp = accumulator();
+        comp.save_snapshot(&path).unwrap();
+        let restored = IComp::load_snapshot(&path).unwrap();
+        assert_eq!(comp.program, restored.program);
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_garbage() {
+        let newer = accumulator().to_snapshot().replacen("snapshot 1", "snapshot 99", 1);
+        assert!(IComp::<i64>::from_snapshot(&newer).unwrap_err().contains("newer"));
+        assert_eq!(Err("not an intcode snapshot".to_string()),
+                   IComp::<i64>::from_snapshot("hello").map(|_| ()));
+    }
+}
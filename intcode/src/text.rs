@@ -0,0 +1,28 @@
+//! Helpers shared by the plain-text file formats (traces, snapshots).
+
+use std::str::FromStr;
+
+pub fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+    values.map(|v| v.to_string()).collect::<Vec<String>>().join(",")
+}
+
+pub fn parse_list<T: FromStr>(s: &str) -> Result<Vec<T>, String> {
+    if s.is_empty() {
+        return Ok(Vec::new());
+    }
+    s.split(',')
+        .map(|n| n.parse::<T>().map_err(|_| format!("could not parse >{}<", n)))
+        .collect()
+}
+
+pub fn parse_one<T: FromStr>(s: &str) -> Result<T, String> {
+    s.parse::<T>().map_err(|_| format!("could not parse >{}<", s))
+}
+
+/// Reads the next line as `<name> <value>`, returning the value.
+pub fn field<'a>(lines: &mut impl Iterator<Item = &'a str>, name: &str) -> Result<&'a str, String> {
+    lines.next()
+        .and_then(|line| line.strip_prefix(name))
+        .and_then(|rest| rest.strip_prefix(' ').or(Some(rest).filter(|r| r.is_empty())))
+        .ok_or_else(|| format!("missing >{}< line", name))
+}
@@ -0,0 +1,461 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::disasm::ParamKind;
+use crate::isa::InstructionSet;
+use crate::text::{field, join, parse_list, parse_one};
+use crate::{Cont, Fault, IComp, IcompError, Instr, Word};
+
+const HEADER: &str = "intcode-trace 1";
+
+/// One executed instruction. Read operands are recorded as the values
+/// they resolved to and write operands as the addresses they targeted.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct TraceEntry<W = i64> {
+    pub addr: usize,
+    pub opval: W,
+    pub operands: Vec<W>,
+    pub writes: Vec<(usize, W)>,
+    pub input: Option<W>,
+    pub output: Option<W>,
+    /// Set on the last entry of a run that ended in a fault.
+    pub fault: Option<Fault>,
+}
+
+/// Everything needed to re-run a traced execution: the machine state
+/// when tracing started and every instruction executed since.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Trace<W = i64> {
+    pub start_iptr: usize,
+    pub start_relative_base: W,
+    pub image: Vec<W>,
+    pub entries: Vec<TraceEntry<W>>,
+}
+
+impl<W: Word> Trace<W> {
+    fn starting_from(comp: &IComp<W>) -> Self {
+        Self {
+            start_iptr: comp.iptr,
+            start_relative_base: comp.relative_base,
+            image: comp.program.clone(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// The input values the traced run consumed, in order.
+    pub fn inputs(&self) -> Vec<W> {
+        self.entries.iter().filter_map(|e| e.input).collect()
+    }
+
+    pub fn outputs(&self) -> Vec<W> {
+        self.entries.iter().filter_map(|e| e.output).collect()
+    }
+
+    pub(crate) fn record_write(&mut self, addr: usize, value: W) {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.writes.push((addr, value));
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+impl<W: Word> fmt::Display for TraceEntry<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.addr, self.opval)?;
+        if !self.operands.is_empty() {
+            write!(f, " args={}", join(self.operands.iter()))?;
+        }
+        if !self.writes.is_empty() {
+            let writes = self.writes.iter().map(|(a, v)| format!("{}:{}", a, v));
+            write!(f, " write={}", join(writes))?;
+        }
+        if let Some(value) = self.input {
+            write!(f, " in={}", value)?;
+        }
+        if let Some(value) = self.output {
+            write!(f, " out={}", value)?;
+        }
+        if let Some(fault) = self.fault {
+            write!(f, " fault={}", fault_token(fault))?;
+        }
+        Ok(())
+    }
+}
+
+// Faults as single words, so they fit the space-separated entry format.
+fn fault_token(fault: Fault) -> String {
+    match fault {
+        Fault::UnknownOpcode => "unknown-opcode".to_string(),
+        Fault::UnknownParamMode(mode) => format!("param-mode:{}", mode),
+        Fault::ImmediateModeWrite => "immediate-write".to_string(),
+        Fault::NegativeAddress(addr) => format!("negative-address:{}", addr),
+        Fault::AddressOutOfRange(addr) => format!("out-of-range:{}", addr),
+        Fault::Overflow => "overflow".to_string(),
+    }
+}
+
+fn parse_fault(s: &str) -> Result<Fault, String> {
+    match s.split_once(':') {
+        None if s == "unknown-opcode" => Ok(Fault::UnknownOpcode),
+        None if s == "immediate-write" => Ok(Fault::ImmediateModeWrite),
+        None if s == "overflow" => Ok(Fault::Overflow),
+        Some(("param-mode", mode)) => Ok(Fault::UnknownParamMode(parse_one(mode)?)),
+        Some(("negative-address", addr)) => Ok(Fault::NegativeAddress(parse_one(addr)?)),
+        Some(("out-of-range", addr)) => Ok(Fault::AddressOutOfRange(parse_one(addr)?)),
+        _ => Err(format!("unknown fault >{}<", s)),
+    }
+}
+
+impl<W: Word> fmt::Display for Trace<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "iptr {}", self.start_iptr)?;
+        writeln!(f, "relative-base {}", self.start_relative_base)?;
+        writeln!(f, "image {}", join(self.image.iter()))?;
+        for entry in self.entries.iter() {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Word> FromStr for TraceEntry<W> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut fields = s.split_whitespace();
+        let mut entry = TraceEntry {
+            addr: parse_one(fields.next().ok_or("missing address")?)?,
+            opval: parse_one(fields.next().ok_or("missing opcode")?)?,
+            ..TraceEntry::default()
+        };
+        for field in fields {
+            let (key, value) = field.split_once('=')
+                .ok_or_else(|| format!("malformed field >{}<", field))?;
+            match key {
+                "args" => entry.operands = parse_list(value)?,
+                "write" => {
+                    entry.writes = value.split(',')
+                        .map(|w| {
+                            let (addr, val) = w.split_once(':')
+                                .ok_or_else(|| format!("malformed write >{}<", w))?;
+                            Ok((parse_one(addr)?, parse_one(val)?))
+                        })
+                        .collect::<Result<Vec<(usize, W)>, String>>()?;
+                },
+                "in" => entry.input = Some(parse_one(value)?),
+                "out" => entry.output = Some(parse_one(value)?),
+                "fault" => entry.fault = Some(parse_fault(value)?),
+                _ => return Err(format!("unknown field >{}<", key)),
+            }
+        }
+        Ok(entry)
+    }
+}
+
+impl<W: Word> FromStr for Trace<W> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(format!("not a trace file; expected header >{}<", HEADER));
+        }
+        let start_iptr = parse_one(field(&mut lines, "iptr")?)?;
+        let start_relative_base = parse_one(field(&mut lines, "relative-base")?)?;
+        let image = parse_list(field(&mut lines, "image")?)?;
+        let entries = lines
+            .enumerate()
+            .map(|(i, line)| line.parse().map_err(|err| format!("entry {}: {}", i, err)))
+            .collect::<Result<Vec<TraceEntry<W>>, String>>()?;
+        Ok(Trace { start_iptr, start_relative_base, image, entries })
+    }
+}
+
+/// Why a replay failed to reproduce its trace.
+#[derive(PartialEq, Debug, Clone)]
+pub enum ReplayError<W = i64> {
+    Diverged {
+        index: usize,
+        expected: Box<TraceEntry<W>>,
+        actual: Option<Box<TraceEntry<W>>>,
+    },
+    Fault(IcompError),
+}
+
+impl<W: Word> fmt::Display for ReplayError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Diverged { index, expected, actual: Some(actual) } => {
+                write!(f, "step {} diverged: expected >{}<, got >{}<", index, expected, actual)
+            },
+            ReplayError::Diverged { index, expected, actual: None } => {
+                write!(f, "step {} diverged: expected >{}<, but the run stopped", index, expected)
+            },
+            ReplayError::Fault(err) => write!(f, "replay faulted: {}", err),
+        }
+    }
+}
+
+impl<W: Word> std::error::Error for ReplayError<W> {}
+
+/// Re-runs a trace from its recorded starting state, feeding back the
+/// inputs it consumed and checking every step against the recording.
+/// Returns the machine as it stood after the last traced instruction; a
+/// trace that ends in a fault must fault the same way on replay.
+pub fn replay<W: Word>(trace: &Trace<W>) -> Result<IComp<W>, ReplayError<W>> {
+    replay_on(IComp::from_words(trace.image.clone()), trace)
+}
+
+/// Replays a trace recorded on a machine with its own instruction set.
+pub fn replay_with<W: Word>(trace: &Trace<W>, isa: &InstructionSet<W>) -> Result<IComp<W>, ReplayError<W>> {
+    let mut comp = IComp::from_words(trace.image.clone());
+    comp.set_instruction_set(isa.clone());
+    replay_on(comp, trace)
+}
+
+fn replay_on<W: Word>(mut comp: IComp<W>, trace: &Trace<W>) -> Result<IComp<W>, ReplayError<W>> {
+    comp.iptr = trace.start_iptr;
+    comp.relative_base = trace.start_relative_base;
+    trace.inputs().into_iter().for_each(|value| comp.push_input(value));
+    comp.start_trace();
+    for (index, expected) in trace.entries.iter().enumerate() {
+        if comp.exec_state == Cont::NeedsInput && !comp.input.is_empty() {
+            comp.exec_state = Cont::Continue;
+        }
+        let before = comp.trace.as_ref().map_or(0, |t| t.entries.len());
+        if comp.exec_state == Cont::Continue {
+            match comp.step() {
+                Ok(Some(value)) => comp.output.push(value),
+                Ok(None) => {},
+                Err(err) if expected.fault.is_none() => return Err(ReplayError::Fault(err)),
+                Err(_) => {},
+            }
+        }
+        let actual = comp.trace.as_ref()
+            .filter(|t| t.entries.len() > before)
+            .and_then(|t| t.entries.last().cloned());
+        if actual.as_ref() != Some(expected) {
+            return Err(ReplayError::Diverged {
+                index,
+                expected: Box::new(expected.clone()),
+                actual: actual.map(Box::new),
+            });
+        }
+    }
+    comp.trace = None;
+    Ok(comp)
+}
+
+impl<W: Word> IComp<W> {
+    /// Starts recording every instruction executed from here on.
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Trace::starting_from(self));
+    }
+
+    pub fn take_trace(&mut self) -> Option<Trace<W>> {
+        self.trace.take()
+    }
+
+    fn resolved_operands(&self, instr: Instr) -> Vec<W> {
+        let params = self.isa.get(instr.opcode).map_or(&[][..], |op| op.params);
+        params.iter()
+            .enumerate()
+            .map(|(i, kind)| {
+                let raw = self.read(self.iptr + i + 1);
+                let resolved = match kind {
+                    ParamKind::Read => self.param_val(raw, instr.modes[i]),
+                    ParamKind::Write => self.param_addr(raw, instr.modes[i]).map(|a| W::from_i64(a as i64)),
+                };
+                resolved.unwrap_or(raw)
+            })
+            .collect()
+    }
+
+    /// Wraps a single instruction with trace bookkeeping. Instructions
+    /// that block on input leave no entry behind; one that faults keeps
+    /// its entry, marked with the fault.
+    pub(crate) fn traced_step(&mut self) -> Result<Option<W>, IcompError> {
+        let entry = TraceEntry {
+            addr: self.iptr,
+            opval: self.read(self.iptr),
+            operands: self.resolved_operands(self.instr_at(self.iptr)),
+            ..TraceEntry::default()
+        };
+        let next_input = self.input.front().copied();
+        let queued = self.input.len();
+        if let Some(trace) = self.trace.as_mut() {
+            trace.entries.push(entry);
+        }
+        self.in_traced_step = true;
+        let result = self.untraced_step();
+        self.in_traced_step = false;
+        let blocked = self.exec_state == Cont::NeedsInput;
+        let consumed = self.input.len() < queued;
+        if let Some(trace) = self.trace.as_mut() {
+            match (&result, blocked) {
+                (Ok(output), false) => {
+                    let entry = trace.entries.last_mut().expect("entry was just pushed");
+                    entry.output = *output;
+                    entry.input = next_input.filter(|_| consumed);
+                },
+                (Err(err), _) => {
+                    let entry = trace.entries.last_mut().expect("entry was just pushed");
+                    entry.fault = Some(err.fault);
+                },
+                (Ok(_), true) => {
+                    trace.entries.pop();
+                },
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod trace_tests {
+    use super::*;
+    use crate::asm;
+    use crate::fixtures::with_sub;
+
+    fn traced_run(source: &str, inputs: &[i64]) -> IComp {
+        let mut comp = IComp::from_program(asm::assemble(source).expect("valid source"));
+        inputs.iter().for_each(|&v| comp.push_input(v));
+        comp.start_trace();
+        comp.execute().expect("program faulted");
+        comp
+    }
+
+    const DOUBLER: &str = "
+        loop: in [x]
+              mul [x], 2, [x]
+              out [x]
+              jnz 1, loop
+        x:    data 0
+    ";
+
+    #[test]
+    fn records_operands_writes_and_io() {
+        let mut comp = traced_run(DOUBLER, &[21]);
+        let trace = comp.take_trace().unwrap();
+        assert_eq!(
+            vec![
+                "0 3 args=11 write=11:21 in=21",
+                "2 1002 args=21,2,11 write=11:42",
+                "6 4 args=42 out=42",
+                "8 1105 args=1,0",
+            ],
+            trace.entries.iter().map(|e| e.to_string()).collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn blocked_input_is_not_recorded_as_a_step() {
+        let mut comp = traced_run(DOUBLER, &[]);
+        assert!(comp.take_trace().unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn trace_survives_a_round_trip_through_text() {
+        let mut comp = traced_run(DOUBLER, &[1, 2, 3]);
+        let trace = comp.take_trace().unwrap();
+        assert_eq!(Ok(trace.clone()), trace.to_string().parse::<Trace>());
+        assert_eq!(vec![1, 2, 3], trace.inputs());
+        assert_eq!(vec![2, 4, 6], trace.outputs());
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_run() {
+        let mut comp = traced_run(DOUBLER, &[5, 6]);
+        let trace = comp.take_trace().unwrap();
+        let replayed = replay(&trace).expect("replay matches");
+        assert_eq!(comp.program, replayed.program);
+        assert_eq!(comp.output, replayed.output);
+        assert_eq!(comp.iptr, replayed.iptr);
+    }
+
+    #[test]
+    fn replay_reports_the_first_divergent_step() {
+        let mut comp = traced_run(DOUBLER, &[5]);
+        let mut trace = comp.take_trace().unwrap();
+        trace.image[4] = 3;
+        match replay(&trace) {
+            Err(ReplayError::Diverged { index, .. }) => assert_eq!(1, index),
+            other => panic!("expected divergence, got {:?}", other),
+        }
+    }
+
+    const BAD_JUMP: &str = "
+        in [x]
+        out [x]
+        jnz 1, [x]
+        x: data 0
+    ";
+
+    #[test]
+    fn keeps_the_faulting_instruction_marked_with_its_fault() {
+        let mut comp = IComp::from_program(asm::assemble(BAD_JUMP).unwrap());
+        comp.push_input(-4);
+        comp.start_trace();
+        assert_eq!(Fault::NegativeAddress(-4), comp.execute().unwrap_err().fault);
+        let trace = comp.take_trace().unwrap();
+        assert_eq!(3, trace.entries.len());
+        assert_eq!("4 105 args=1,-4 fault=negative-address:-4", trace.entries[2].to_string());
+        assert_eq!(Ok(trace.clone()), trace.to_string().parse::<Trace>());
+    }
+
+    #[test]
+    fn replay_expects_the_recorded_fault() {
+        let mut comp = IComp::from_program(asm::assemble(BAD_JUMP).unwrap());
+        comp.push_input(-4);
+        comp.start_trace();
+        comp.execute().unwrap_err();
+        let mut trace = comp.take_trace().unwrap();
+        let replayed = replay(&trace).expect("replay faults the same way");
+        assert_eq!(vec![-4], replayed.output);
+        trace.entries[2].fault = None;
+        assert!(matches!(replay(&trace), Err(ReplayError::Fault(_))));
+        trace.entries[2].fault = Some(Fault::Overflow);
+        assert!(matches!(replay(&trace), Err(ReplayError::Diverged { index: 2, .. })));
+    }
+
+    #[test]
+    fn replays_with_the_recorded_instruction_set() {
+        let mut comp = IComp::from_program(vec![1110, 50, 8, 7, 4, 7, 99, 0]);
+        comp.set_instruction_set(with_sub());
+        comp.start_trace();
+        comp.execute().unwrap();
+        let trace = comp.take_trace().unwrap();
+        assert_eq!(vec![42], replay_with(&trace, &with_sub()).unwrap().output);
+        assert!(matches!(replay(&trace), Err(ReplayError::Fault(_))));
+    }
+
+    #[test]
+    fn leaves_writes_between_steps_out_of_the_trace() {
+        let mut comp = IComp::from_program(asm::assemble("out 1\nhlt").unwrap());
+        comp.start_trace();
+        comp.step().unwrap();
+        comp.write(3, 5).unwrap();
+        comp.execute().unwrap();
+        let trace = comp.take_trace().unwrap();
+        assert_eq!(Vec::<(usize, i64)>::new(), trace.entries[0].writes);
+        assert!(replay(&trace).is_ok());
+    }
+
+    #[test]
+    fn rejects_files_without_a_header() {
+        assert!("0 99\n".parse::<Trace>().is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Cont, IComp, IcompError, Word};
+
+/// Which kind of memory access a watchpoint fires on.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+/// Fires when an instruction reads or writes a cell in `start..=end`
+/// through one of its operands. Instruction fetches do not count.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Watchpoint {
+    pub start: usize,
+    pub end: usize,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    pub fn new(start: usize, end: usize, kind: WatchKind) -> Self {
+        Self { start, end, kind }
+    }
+
+    fn fires(&self, addr: usize, kind: WatchKind) -> bool {
+        (self.start..=self.end).contains(&addr) && (self.kind == WatchKind::Access || self.kind == kind)
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.start)?;
+        if self.end != self.start {
+            write!(f, "-{}", self.end)?;
+        }
+        match self.kind {
+            WatchKind::Read => write!(f, ":r"),
+            WatchKind::Write => write!(f, ":w"),
+            WatchKind::Access => write!(f, ":rw"),
+        }
+    }
+}
+
+/// Parses `addr` or `start-end`, optionally followed by `:r`, `:w` or
+/// `:rw` (the default).
+impl FromStr for Watchpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (range, kind) = s.split_once(':').unwrap_or((s, "rw"));
+        let kind = match kind {
+            "r" => WatchKind::Read,
+            "w" => WatchKind::Write,
+            "rw" => WatchKind::Access,
+            _ => return Err(format!("unknown watch kind >{}<; expected r, w or rw", kind)),
+        };
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let parse = |n: &str| n.parse::<usize>().map_err(|_| format!("could not parse >{}<", n));
+        let (start, end) = (parse(start)?, parse(end)?);
+        if end < start {
+            return Err(format!("empty range >{}<", range));
+        }
+        Ok(Watchpoint::new(start, end, kind))
+    }
+}
+
+/// Something the watcher saw, tagged with the address of the
+/// instruction responsible.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Event<W = i64> {
+    Read { iptr: usize, addr: usize, value: W },
+    Write { iptr: usize, addr: usize, old: W, new: W },
+    /// A write to a cell that had already been executed as part of an
+    /// instruction, either as its opcode or as an operand.
+    SelfModified { iptr: usize, addr: usize, old: W, new: W },
+}
+
+impl<W: Word> fmt::Display for Event<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Read { iptr, addr, value } => {
+                write!(f, "{:04}: read [{}] = {}", iptr, addr, value)
+            },
+            Event::Write { iptr, addr, old, new } => {
+                write!(f, "{:04}: write [{}] {} -> {}", iptr, addr, old, new)
+            },
+            Event::SelfModified { iptr, addr, old, new } => {
+                write!(f, "{:04}: self-modifying write [{}] {} -> {}", iptr, addr, old, new)
+            },
+        }
+    }
+}
+
+/// Watchpoints and the self-modification detector, together with
+/// everything they have reported so far.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Watch<W = i64> {
+    pub watchpoints: Vec<Watchpoint>,
+    pub detect_self_modification: bool,
+    pub events: Vec<Event<W>>,
+    executed: Vec<bool>,
+}
+
+impl<W> Watch<W> {
+    fn fires(&self, addr: usize, kind: WatchKind) -> bool {
+        self.watchpoints.iter().any(|wp| wp.fires(addr, kind))
+    }
+
+    fn was_executed(&self, addr: usize) -> bool {
+        self.executed.get(addr).copied().unwrap_or(false)
+    }
+
+    fn mark_executed(&mut self, cells: std::ops::RangeInclusive<usize>) {
+        if self.executed.len() <= *cells.end() {
+            self.executed.resize(cells.end() + 1, false);
+        }
+        self.executed[cells].iter_mut().for_each(|cell| *cell = true);
+    }
+}
+
+impl<W: Word> IComp<W> {
+    fn watch_mut(&mut self) -> &mut Watch<W> {
+        self.watch.get_or_insert_with(Watch::default)
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watch_mut().watchpoints.push(watchpoint);
+    }
+
+    /// Removes a watchpoint, returning whether it was set.
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let watchpoints = &mut self.watch_mut().watchpoints;
+        let before = watchpoints.len();
+        watchpoints.retain(|wp| wp != watchpoint);
+        watchpoints.len() < before
+    }
+
+    /// Starts reporting writes to cells that have already run as code.
+    /// Only instructions executed from here on count as code.
+    pub fn detect_self_modification(&mut self) {
+        self.watch_mut().detect_self_modification = true;
+    }
+
+    /// Everything reported since watching started.
+    pub fn watch_events(&self) -> &[Event<W>] {
+        self.watch.as_ref().map_or(&[], |watch| &watch.events)
+    }
+
+    pub fn take_watch(&mut self) -> Option<Watch<W>> {
+        self.watch.take()
+    }
+
+    /// Wraps a single instruction, checking the cells its operands touch
+    /// against the watchpoints. Instructions that fault or block on input
+    /// report nothing.
+    pub(crate) fn watched_step(&mut self) -> Result<Option<W>, IcompError> {
+        let iptr = self.iptr;
+        let params = self.isa.get(self.instr_at(iptr).opcode).map_or(0, |op| op.params.len());
+        let (reads, writes) = self.operand_accesses();
+        let reads = reads.into_iter().map(|addr| (addr, self.read(addr))).collect::<Vec<(usize, W)>>();
+        let old = writes.iter().map(|&addr| self.read(addr)).collect::<Vec<W>>();
+        let result = self.execute_instruction();
+        if result.is_err() || self.exec_state == Cont::NeedsInput {
+            return result;
+        }
+        let new = writes.iter().map(|&addr| self.read(addr)).collect::<Vec<W>>();
+        let watch = self.watch.as_mut().expect("watching was started");
+        watch.mark_executed(iptr..=iptr + params);
+        for (addr, value) in reads {
+            if watch.fires(addr, WatchKind::Read) {
+                watch.events.push(Event::Read { iptr, addr, value });
+            }
+        }
+        for ((addr, old), new) in writes.into_iter().zip(old).zip(new) {
+            if watch.fires(addr, WatchKind::Write) {
+                watch.events.push(Event::Write { iptr, addr, old, new });
+            }
+            if watch.detect_self_modification && watch.was_executed(addr) {
+                watch.events.push(Event::SelfModified { iptr, addr, old, new });
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use super::*;
+    use crate::asm;
+    use crate::fixtures::{COUNTDOWN, PATCH_AND_LOOP};
+
+    fn countdown(n: i64) -> IComp {
+        let mut comp = IComp::from_program(asm::assemble(COUNTDOWN).expect("valid source"));
+        comp.push_input(n);
+        comp
+    }
+
+    #[test]
+    fn parses_addresses_ranges_and_kinds() {
+        assert_eq!(Ok(Watchpoint::new(10, 10, WatchKind::Access)), "10".parse());
+        assert_eq!(Ok(Watchpoint::new(4, 8, WatchKind::Write)), "4-8:w".parse());
+        assert_eq!("4-8:r", "4-8:r".parse::<Watchpoint>().unwrap().to_string());
+        assert!("8-4".parse::<Watchpoint>().is_err());
+        assert!("4:x".parse::<Watchpoint>().is_err());
+    }
+
+    #[test]
+    fn reports_reads_and_writes_on_watched_cells() {
+        let mut comp = countdown(2);
+        comp.add_watchpoint("10:w".parse().unwrap());
+        comp.execute().unwrap();
+        assert_eq!(
+            vec!["0000: write [10] 0 -> 2", "0002: write [10] 2 -> 1", "0002: write [10] 1 -> 0"],
+            comp.watch_events().iter().map(|e| e.to_string()).collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn read_watchpoints_ignore_writes_and_unwatched_cells() {
+        let mut comp = countdown(2);
+        comp.add_watchpoint(Watchpoint::new(0, 9, WatchKind::Access));
+        comp.add_watchpoint(Watchpoint::new(10, 10, WatchKind::Read));
+        comp.execute().unwrap();
+        let reads = comp.watch_events().iter()
+            .filter(|e| matches!(e, Event::Read { addr: 10, .. }))
+            .count();
+        assert_eq!(4, reads);
+        assert_eq!(4, comp.watch_events().len());
+    }
+
+    #[test]
+    fn removed_watchpoints_stop_firing() {
+        let mut comp = countdown(2);
+        let watchpoint = "10".parse().unwrap();
+        comp.add_watchpoint(watchpoint);
+        assert!(comp.remove_watchpoint(&watchpoint));
+        assert!(!comp.remove_watchpoint(&watchpoint));
+        comp.execute().unwrap();
+        assert!(comp.watch_events().is_empty());
+    }
+
+    #[test]
+    fn detects_writes_over_executed_code() {
+        let mut comp = IComp::from_program(asm::assemble(PATCH_AND_LOOP).unwrap());
+        comp.detect_self_modification();
+        comp.execute().unwrap();
+        assert_eq!(
+            &[Event::SelfModified { iptr: 2, addr: 0, old: 104, new: 99 }],
+            comp.watch_events()
+        );
+    }
+
+    #[test]
+    fn patching_code_before_it_runs_is_not_self_modification() {
+        let mut comp = IComp::from_program(vec![1002, 4, 3, 4, 33]);
+        comp.detect_self_modification();
+        comp.execute().unwrap();
+        assert!(comp.watch_events().is_empty());
+        assert_eq!(99, comp.program[4]);
+    }
+}
@@ -0,0 +1,140 @@
+//! The integer types an `IComp` can compute with.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+use crate::isa::InstructionSet;
+
+/// A machine word. `add` and `mul` return `None` to report an overflow,
+/// which faults the machine; the plain integer types wrap instead, so a
+/// program behaves the same on every host.
+pub trait Word: Copy + Ord + Default + fmt::Debug + fmt::Display + FromStr + Send + 'static {
+    fn from_i64(n: i64) -> Self;
+    /// Widens the word to `i128`, which every word type fits in.
+    fn to_i128(self) -> i128;
+    fn add(self, rhs: Self) -> Option<Self>;
+    fn mul(self, rhs: Self) -> Option<Self>;
+
+    /// The standard instruction set for this word type. The built-in
+    /// words share one table between all their machines.
+    fn standard_isa() -> Arc<InstructionSet<Self>> {
+        Arc::new(InstructionSet::standard())
+    }
+}
+
+/// Wraps an integer so that arithmetic overflow faults the machine with
+/// `Fault::Overflow` rather than wrapping around.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Default, Hash)]
+pub struct Checked<T>(pub T);
+
+impl<T: fmt::Display> fmt::Display for Checked<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: FromStr> FromStr for Checked<T> {
+    type Err = T::Err;
+
+    fn from_str(s: &str) -> Result<Self, T::Err> {
+        s.parse().map(Checked)
+    }
+}
+
+macro_rules! impl_word {
+    ($($t:ty),*) => {$(
+        impl Word for $t {
+            fn from_i64(n: i64) -> Self {
+                <$t>::from(n)
+            }
+
+            fn to_i128(self) -> i128 {
+                i128::from(self)
+            }
+
+            fn add(self, rhs: Self) -> Option<Self> {
+                Some(self.wrapping_add(rhs))
+            }
+
+            fn mul(self, rhs: Self) -> Option<Self> {
+                Some(self.wrapping_mul(rhs))
+            }
+
+            fn standard_isa() -> Arc<InstructionSet<Self>> {
+                static ISA: OnceLock<Arc<InstructionSet<$t>>> = OnceLock::new();
+                ISA.get_or_init(|| Arc::new(InstructionSet::standard())).clone()
+            }
+        }
+
+        impl Word for Checked<$t> {
+            fn from_i64(n: i64) -> Self {
+                Checked(<$t>::from(n))
+            }
+
+            fn to_i128(self) -> i128 {
+                i128::from(self.0)
+            }
+
+            fn add(self, rhs: Self) -> Option<Self> {
+                self.0.checked_add(rhs.0).map(Checked)
+            }
+
+            fn mul(self, rhs: Self) -> Option<Self> {
+                self.0.checked_mul(rhs.0).map(Checked)
+            }
+
+            fn standard_isa() -> Arc<InstructionSet<Self>> {
+                static ISA: OnceLock<Arc<InstructionSet<Checked<$t>>>> = OnceLock::new();
+                ISA.get_or_init(|| Arc::new(InstructionSet::standard())).clone()
+            }
+        }
+    )*};
+}
+
+impl_word!(i64, i128);
+
+#[cfg(test)]
+mod word_tests {
+    use super::*;
+    use crate::{Fault, IComp, IcompError};
+
+    #[test]
+    fn plain_words_wrap_on_overflow() {
+        assert_eq!(Some(i64::MIN), Word::add(i64::MAX, 1));
+        assert_eq!(Some(-2), Word::mul(i64::MAX, 2i64));
+        assert_eq!(Some(i64::MAX as i128 * 2), Word::mul(i64::MAX as i128, 2));
+    }
+
+    #[test]
+    fn checked_words_report_overflow() {
+        assert_eq!(None, Word::add(Checked(i64::MAX), Checked(1)));
+        assert_eq!(Some(Checked(6)), Word::mul(Checked(2i128), Checked(3)));
+    }
+
+    /// Squares its input twice and prints the result.
+    fn fourth_power<W: Word>(n: i64) -> Result<Option<W>, IcompError> {
+        let program = [3, 13, 2, 13, 13, 13, 2, 13, 13, 13, 4, 13, 99, 0];
+        let mut comp = IComp::from_words(program.iter().map(|&w| W::from_i64(w)).collect());
+        comp.push_input(W::from_i64(n));
+        comp.execute()?;
+        Ok(comp.output.first().copied())
+    }
+
+    #[test]
+    fn machines_compute_with_their_word_type() {
+        let n = 100_000;
+        assert_eq!(Ok(Some(10i128.pow(20))), fourth_power::<i128>(n));
+        assert_eq!(Ok(Some(10i128.pow(20) as i64)), fourth_power::<i64>(n));
+        assert_eq!(Ok(Some(Checked(10_000))), fourth_power::<Checked<i64>>(10));
+        let err = fourth_power::<Checked<i64>>(n).unwrap_err();
+        assert_eq!(Fault::Overflow, err.fault);
+        assert_eq!(Ok(Some(Checked(10i128.pow(20)))), fourth_power::<Checked<i128>>(n));
+    }
+
+    #[test]
+    fn checked_words_print_and_parse_like_their_inner_type() {
+        assert_eq!("-12", Checked(-12i64).to_string());
+        assert_eq!(Ok(Checked(40i128)), "40".parse());
+    }
+}